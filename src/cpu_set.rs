@@ -0,0 +1,286 @@
+use crate::{Error, WinResult};
+use std::{fmt, iter::FromIterator, ptr::null_mut};
+use winapi::{
+    shared::{minwindef::DWORD, winerror::ERROR_INSUFFICIENT_BUFFER},
+    um::{
+        errhandlingapi::GetLastError,
+        sysinfoapi::GetLogicalProcessorInformationEx,
+        winnt::{
+            RelationProcessorCore,
+            GROUP_AFFINITY,
+            PROCESSOR_NUMBER,
+            SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX,
+        },
+    },
+};
+
+/// A set of logical processors, such as a process or thread affinity.
+///
+/// Bit `n` of the underlying mask represents the logical processor `n` of a processor group, so
+/// the set can hold indices `0` to `63`.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct CpuSet {
+    mask: u64,
+}
+
+impl CpuSet {
+    /// The number of processors a set can hold.
+    pub const CAPACITY: usize = 64;
+
+    /// Creates an empty set.
+    pub fn new() -> CpuSet {
+        CpuSet { mask: 0 }
+    }
+
+    /// Creates a set from a raw affinity mask.
+    pub fn from_mask(mask: u64) -> CpuSet {
+        CpuSet { mask }
+    }
+
+    /// Returns the raw affinity mask of the set.
+    pub fn mask(&self) -> u64 {
+        self.mask
+    }
+
+    /// Returns true if the set contains the processor.
+    pub fn contains(&self, cpu: usize) -> bool {
+        cpu < CpuSet::CAPACITY && self.mask & (1 << cpu) != 0
+    }
+
+    /// Adds a processor to the set. Returns true if it was not already present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not below `CpuSet::CAPACITY`.
+    pub fn insert(&mut self, cpu: usize) -> bool {
+        assert!(cpu < CpuSet::CAPACITY, "CPU index out of range: {}", cpu);
+        let present = self.contains(cpu);
+        self.mask |= 1 << cpu;
+        !present
+    }
+
+    /// Removes a processor from the set. Returns true if it was present.
+    pub fn remove(&mut self, cpu: usize) -> bool {
+        let present = self.contains(cpu);
+        if present {
+            self.mask &= !(1 << cpu);
+        }
+        present
+    }
+
+    /// Returns the number of processors in the set.
+    pub fn len(&self) -> usize {
+        self.mask.count_ones() as usize
+    }
+
+    /// Returns true if the set contains no processors.
+    pub fn is_empty(&self) -> bool {
+        self.mask == 0
+    }
+
+    /// Returns an iterator over the indices of the processors in the set, in ascending order.
+    pub fn iter(&self) -> CpuSetIter {
+        CpuSetIter { mask: self.mask }
+    }
+}
+
+impl fmt::Debug for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl From<u64> for CpuSet {
+    fn from(mask: u64) -> CpuSet {
+        CpuSet::from_mask(mask)
+    }
+}
+
+impl FromIterator<usize> for CpuSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> CpuSet {
+        let mut set = CpuSet::new();
+        for cpu in iter {
+            set.insert(cpu);
+        }
+        set
+    }
+}
+
+impl IntoIterator for &CpuSet {
+    type Item = usize;
+    type IntoIter = CpuSetIter;
+
+    fn into_iter(self) -> CpuSetIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the processor indices of a `CpuSet`.
+#[derive(Debug, Clone)]
+pub struct CpuSetIter {
+    mask: u64,
+}
+
+impl Iterator for CpuSetIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.mask == 0 {
+            None
+        } else {
+            let cpu = self.mask.trailing_zeros() as usize;
+            self.mask &= self.mask - 1;
+            Some(cpu)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.mask.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+/// Identifies a logical processor across all processor groups.
+///
+/// Systems with more than 64 logical processors split them into groups, and a processor index
+/// alone only identifies a processor within a group.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ProcessorNumber {
+    /// The processor group.
+    pub group: u16,
+    /// The index of the processor within its group.
+    pub number: u8,
+}
+
+impl ProcessorNumber {
+    /// Creates a processor number from a group and an index within that group.
+    pub fn new(group: u16, number: u8) -> ProcessorNumber {
+        ProcessorNumber { group, number }
+    }
+}
+
+impl From<PROCESSOR_NUMBER> for ProcessorNumber {
+    fn from(pn: PROCESSOR_NUMBER) -> ProcessorNumber {
+        ProcessorNumber {
+            group: pn.Group,
+            number: pn.Number,
+        }
+    }
+}
+
+impl From<ProcessorNumber> for PROCESSOR_NUMBER {
+    fn from(pn: ProcessorNumber) -> PROCESSOR_NUMBER {
+        PROCESSOR_NUMBER {
+            Group: pn.group,
+            Number: pn.number,
+            Reserved: 0,
+        }
+    }
+}
+
+/// A set of logical processors within a single processor group.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct GroupAffinity {
+    /// The processor group.
+    pub group: u16,
+    /// The affinity mask of the processors of the group.
+    pub mask: usize,
+}
+
+impl GroupAffinity {
+    /// Creates a group affinity from a group and an affinity mask of processors within that group.
+    pub fn new(group: u16, mask: usize) -> GroupAffinity {
+        GroupAffinity { group, mask }
+    }
+
+    /// Returns the processors of the group as a `CpuSet`.
+    pub fn cpu_set(&self) -> CpuSet {
+        CpuSet::from_mask(self.mask as u64)
+    }
+}
+
+impl From<GROUP_AFFINITY> for GroupAffinity {
+    fn from(ga: GROUP_AFFINITY) -> GroupAffinity {
+        GroupAffinity {
+            group: ga.Group,
+            mask: ga.Mask as usize,
+        }
+    }
+}
+
+impl From<GroupAffinity> for GROUP_AFFINITY {
+    fn from(ga: GroupAffinity) -> GROUP_AFFINITY {
+        GROUP_AFFINITY {
+            Mask: ga.mask as _,
+            Group: ga.group,
+            Reserved: [0; 3],
+        }
+    }
+}
+
+/// The class of a processor core on a hybrid CPU.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum CoreClass {
+    /// The cores with the lowest efficiency class, which use the least power.
+    Efficiency,
+    /// The cores with the highest efficiency class, which perform best.
+    Performance,
+}
+
+impl CoreClass {
+    /// Returns the set of logical processors of processor group 0 that belong to cores of the
+    /// class.
+    ///
+    /// On CPUs whose cores all share the same efficiency class, both classes contain every core.
+    pub fn cpu_set(self) -> WinResult<CpuSet> {
+        let cores = processor_cores()?;
+        let efficiency_class = match self {
+            CoreClass::Efficiency => cores.iter().map(|&(class, _)| class).min(),
+            CoreClass::Performance => cores.iter().map(|&(class, _)| class).max(),
+        };
+        Ok(CpuSet::from_mask(
+            cores
+                .iter()
+                .filter(|&&(class, _)| Some(class) == efficiency_class)
+                .fold(0, |mask, &(_, core)| mask | core),
+        ))
+    }
+}
+
+/// Returns the efficiency class and processor mask of each processor core of group 0.
+fn processor_cores() -> WinResult<Vec<(u8, u64)>> {
+    unsafe {
+        let mut len: DWORD = 0;
+        let ret = GetLogicalProcessorInformationEx(RelationProcessorCore, null_mut(), &mut len);
+        if ret == 0 && GetLastError() != ERROR_INSUFFICIENT_BUFFER {
+            return Err(Error::last_os_error());
+        }
+
+        let mut buffer: Vec<u64> = vec![0; len as usize / 8 + 1];
+        let ret = GetLogicalProcessorInformationEx(
+            RelationProcessorCore,
+            // The binding declares the non-extended structure, but the buffer is filled with
+            // extended ones.
+            buffer.as_mut_ptr() as _,
+            &mut len,
+        );
+        if ret == 0 {
+            return Err(Error::last_os_error());
+        }
+
+        let mut cores = Vec::new();
+        let mut offset = 0;
+        while offset < len as usize {
+            let info = &*((buffer.as_ptr() as *const u8).add(offset)
+                as *const SYSTEM_LOGICAL_PROCESSOR_INFORMATION_EX);
+            let core = info.u.Processor();
+            // A core is never split across groups, so the first group mask is its only one.
+            let group = &core.GroupMask[0];
+            if group.Group == 0 {
+                cores.push((core.EfficiencyClass, group.Mask as u64));
+            }
+            offset += info.Size as usize;
+        }
+        Ok(cores)
+    }
+}
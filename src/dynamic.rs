@@ -0,0 +1,27 @@
+//! Lookup of system functions that aren't available on every supported version of Windows.
+
+use std::ffi::CString;
+use widestring::WideCString;
+use winapi::{
+    ctypes::c_void,
+    um::libloaderapi::{GetModuleHandleW, GetProcAddress},
+};
+
+/// Returns the address of a function exported by a module already loaded in the current process,
+/// or `None` if either can't be found.
+pub fn proc_address(module: &str, name: &str) -> Option<*mut c_void> {
+    let module = WideCString::from_str(module).ok()?;
+    let name = CString::new(name).ok()?;
+    unsafe {
+        let handle = GetModuleHandleW(module.as_ptr());
+        if handle.is_null() {
+            return None;
+        }
+        let address = GetProcAddress(handle, name.as_ptr());
+        if address.is_null() {
+            None
+        } else {
+            Some(address as *mut c_void)
+        }
+    }
+}
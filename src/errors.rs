@@ -8,6 +8,18 @@ pub enum Error {
     Os(#[cause] io::Error),
     /// No process found during a search.
     NoProcess(String),
+    /// No module found with the name in the process.
+    NoModule(String),
+    /// An address specification couldn't be parsed.
+    InvalidAddress(String),
+    /// The process is the System Idle Process (PID 0) or the System process (PID 4), which can't
+    /// be opened like regular processes.
+    ProtectedSystemProcess(u32),
+    /// A function is forwarded to an API set, like `api-ms-win-core-synch-l1-2-0`, which is only
+    /// resolved to a DLL by the loader of the target process.
+    ApiSetForwarder(String),
+    /// The executable path of the process, whose ID is given, has no file name component.
+    NoProcessName(u32),
     /// An invalid nul value was found in a UTF-8 string.
     NulError(#[cause] ffi::NulError),
     /// An invalid nul value was found in a UTF-16 string vector.
@@ -44,6 +56,17 @@ impl fmt::Display for Error {
                 write!(f, "Windows error: {}", e)
             }
             Error::NoProcess(ref name) => write!(f, "No process found with the name: {}", name),
+            Error::NoModule(ref name) => write!(f, "No module found with the name: {}", name),
+            Error::InvalidAddress(ref spec) => write!(f, "Invalid address: {}", spec),
+            Error::ProtectedSystemProcess(id) => write!(
+                f,
+                "Process {} is a protected system process and can't be opened",
+                id
+            ),
+            Error::ApiSetForwarder(ref target) => {
+                write!(f, "Function forwarded to an API set: {}", target)
+            }
+            Error::NoProcessName(id) => write!(f, "Process {} has no executable file name", id),
             Error::NulError(ref e) => write!(f, "Null byte error: {}", e),
             Error::NulErrorW { ref pos, ref data } => {
                 write!(f, "Null byte UTF-16 error: pos {} in {:?}", pos, data)
@@ -8,12 +8,17 @@ use std::{
 };
 use winapi::{
     shared::minwindef as mw,
-    um::{handleapi as wh, processthreadsapi as wp, winnt},
+    um::{handleapi as wh, processthreadsapi as wp, winbase, winnt},
 };
 
+/// The value of the pseudo-handle returned by `GetCurrentProcess`.
+const CURRENT_PROCESS: isize = -1;
+/// The value of the pseudo-handle returned by `GetCurrentThread`.
+const CURRENT_THREAD: isize = -2;
+
 /// An owning wrapper over a Windows handle.
 ///
-/// Will close the inner handle on `drop`.
+/// Will close the inner handle on `drop`, unless it's a pseudo-handle.
 #[derive(Debug)]
 pub struct Handle(winnt::HANDLE);
 
@@ -23,6 +28,15 @@ impl Handle {
         Handle(handle)
     }
 
+    /// Returns true if the handle is the pseudo-handle of the current process or thread.
+    ///
+    /// Pseudo-handles are only meaningful in the current process and are never closed. Use
+    /// `Handle::duplicate_from` to get a real handle to the same object.
+    pub fn is_pseudo(&self) -> bool {
+        let value = self.0 as isize;
+        value == CURRENT_PROCESS || value == CURRENT_THREAD
+    }
+
     //    pub fn close(self) -> WinResult {
     //        match unsafe { wh::CloseHandle(self.into_raw_handle()) } {
     //            0 => Err(Error::last()),
@@ -47,6 +61,27 @@ impl Handle {
             _ => Ok(Handle(new_handle)),
         }
     }
+
+    /// Sets whether the handle is inherited by child processes.
+    pub fn set_inheritable(&self, inheritable: bool) -> WinResult {
+        self.set_flag(winbase::HANDLE_FLAG_INHERIT, inheritable)
+    }
+
+    /// Sets whether the handle is protected from being closed.
+    ///
+    /// Dropping a protected handle panics, as it can't be closed, so the protection must be
+    /// removed beforehand.
+    pub fn set_protected_from_close(&self, protected: bool) -> WinResult {
+        self.set_flag(winbase::HANDLE_FLAG_PROTECT_FROM_CLOSE, protected)
+    }
+
+    fn set_flag(&self, flag: mw::DWORD, enabled: bool) -> WinResult {
+        let ret = unsafe { wh::SetHandleInformation(self.0, flag, if enabled { flag } else { 0 }) };
+        match ret {
+            0 => Err(Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl AsRawHandle for Handle {
@@ -64,6 +99,9 @@ impl Deref for Handle {
 
 impl Drop for Handle {
     fn drop(&mut self) {
+        if self.is_pseudo() {
+            return;
+        }
         let err = unsafe { wh::CloseHandle(self.0) };
         assert_ne!(err, 0, "{:?}", io::Error::last_os_error());
     }
@@ -2,22 +2,82 @@
 
 #![cfg(windows)]
 
+mod cpu_set;
+mod dynamic;
 pub mod errors;
 mod handle;
+mod net;
+mod ntdll;
 mod process;
+mod user32;
+mod wintrust;
 
 pub use self::{
+    cpu_set::{CoreClass, CpuSet, CpuSetIter, GroupAffinity, ProcessorNumber},
     errors::{Error, WinResult},
     handle::Handle,
+    net::{
+        process_for_tcp_port,
+        process_for_udp_port,
+        process_id_for_tcp_port,
+        process_id_for_udp_port,
+    },
     process::{
+        all_threads_with_owner,
+        module_diff,
+        process_count,
+        processes_with_module,
+        terminate_all_by_name,
+        thread_count,
+        threads_by_process,
+        wait_all,
+        wait_any,
+        wait_for_process,
+        wait_for_process_with_access,
         Access,
+        Architecture,
+        ContextFlags,
+        DebugInfo,
+        GuiObjectCounts,
+        HeapInfo,
+        ImpersonationGuard,
+        ImportedFunction,
+        ImportedModule,
+        IntegrityLevel,
+        IoCounters,
+        IoPriority,
+        MemoryCounters,
+        MemoryRegion,
         Module,
+        ModuleDiff,
         ModuleEntry,
         ModuleInfo,
+        NeverResumedHeuristic,
+        OrderedModule,
+        PeHeaders,
         PriorityClass,
         PriorityLevel,
         Process,
+        ProcessCache,
+        ProcessEntry,
+        ProcessIdentity,
+        ProcessParameters,
+        ProcessTimes,
+        Protection,
+        RemoteAlloc,
+        RemoteThreadFlags,
+        Section,
+        StartupInfo,
+        SuspendGuard,
+        TerminateSummary,
         Thread,
+        ThreadAccess,
+        ThreadEntry,
+        ThreadState,
+        ThreadTimes,
+        Token,
+        WaitResult,
+        WorkingSetLimits,
     },
 };
 use std::mem;
@@ -25,6 +85,7 @@ use winapi::{
     ctypes::c_void,
     shared::minwindef::WORD,
     um::{
+        processthreadsapi::FlushProcessWriteBuffers,
         sysinfoapi::{GetNativeSystemInfo, SYSTEM_INFO},
         winnt::{
             PROCESSOR_ARCHITECTURE_ALPHA,
@@ -157,6 +218,14 @@ impl From<SYSTEM_INFO> for SystemInfo {
     }
 }
 
+/// Flushes the write queue of each processor running a thread of the current process.
+///
+/// This acts as a system-wide memory barrier: writes made by the current process, including to
+/// memory shared with another process, are visible to all processors once it returns.
+pub fn flush_process_write_buffers() {
+    unsafe { FlushProcessWriteBuffers() }
+}
+
 //#[cfg(test)]
 //mod tests {
 //    use super::*;
@@ -0,0 +1,114 @@
+//! Lookup of the processes owning network endpoints.
+
+use crate::{Error, Process, WinResult};
+use std::{io, mem, ptr};
+use winapi::{
+    ctypes::c_void,
+    shared::{
+        iprtrmib::{TCP_TABLE_OWNER_PID_ALL, UDP_TABLE_OWNER_PID},
+        minwindef::{DWORD, FALSE},
+        tcpmib::{MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID, MIB_TCP_STATE_LISTEN},
+        udpmib::{MIB_UDP6ROW_OWNER_PID, MIB_UDPROW_OWNER_PID},
+        winerror::{ERROR_INSUFFICIENT_BUFFER, NO_ERROR},
+        ws2def::{AF_INET, AF_INET6},
+    },
+    um::iphlpapi::{GetExtendedTcpTable, GetExtendedUdpTable},
+};
+
+/// Returns the id of the process owning the local TCP port, over IPv4 or IPv6.
+///
+/// If several sockets are bound to the port, the process listening on it is preferred.
+/// Returns `None` if no socket uses the port.
+pub fn process_id_for_tcp_port(port: u16) -> WinResult<Option<u32>> {
+    let v4 = owner_table::<MIB_TCPROW_OWNER_PID, _>(|table, size| unsafe {
+        GetExtendedTcpTable(table, size, FALSE, AF_INET as _, TCP_TABLE_OWNER_PID_ALL, 0)
+    })?;
+    let v6 = owner_table::<MIB_TCP6ROW_OWNER_PID, _>(|table, size| unsafe {
+        GetExtendedTcpTable(
+            table,
+            size,
+            FALSE,
+            AF_INET6 as _,
+            TCP_TABLE_OWNER_PID_ALL,
+            0,
+        )
+    })?;
+
+    let rows: Vec<(DWORD, DWORD, DWORD)> = v4
+        .iter()
+        .map(|r| (r.dwState, r.dwLocalPort, r.dwOwningPid))
+        .chain(v6.iter().map(|r| (r.dwState, r.dwLocalPort, r.dwOwningPid)))
+        .filter(|&(_, local_port, _)| decode_port(local_port) == port)
+        .collect();
+    Ok(rows
+        .iter()
+        .find(|&&(state, _, _)| state == MIB_TCP_STATE_LISTEN as DWORD)
+        .or_else(|| rows.first())
+        .map(|&(_, _, pid)| pid))
+}
+
+/// Returns the id of the process owning the local UDP port, over IPv4 or IPv6.
+///
+/// Returns `None` if no socket uses the port.
+pub fn process_id_for_udp_port(port: u16) -> WinResult<Option<u32>> {
+    let v4 = owner_table::<MIB_UDPROW_OWNER_PID, _>(|table, size| unsafe {
+        GetExtendedUdpTable(table, size, FALSE, AF_INET as _, UDP_TABLE_OWNER_PID, 0)
+    })?;
+    let v6 = owner_table::<MIB_UDP6ROW_OWNER_PID, _>(|table, size| unsafe {
+        GetExtendedUdpTable(table, size, FALSE, AF_INET6 as _, UDP_TABLE_OWNER_PID, 0)
+    })?;
+
+    Ok(v4
+        .iter()
+        .map(|r| (r.dwLocalPort, r.dwOwningPid))
+        .chain(v6.iter().map(|r| (r.dwLocalPort, r.dwOwningPid)))
+        .find(|&(local_port, _)| decode_port(local_port) == port)
+        .map(|(_, pid)| pid))
+}
+
+/// Opens the process owning the local TCP port. Requests all access.
+///
+/// See `process_id_for_tcp_port`.
+pub fn process_for_tcp_port(port: u16) -> WinResult<Option<Process>> {
+    process_id_for_tcp_port(port)?
+        .map(Process::from_id)
+        .transpose()
+}
+
+/// Opens the process owning the local UDP port. Requests all access.
+///
+/// See `process_id_for_udp_port`.
+pub fn process_for_udp_port(port: u16) -> WinResult<Option<Process>> {
+    process_id_for_udp_port(port)?
+        .map(Process::from_id)
+        .transpose()
+}
+
+/// Ports are stored in network byte order in the low 16 bits.
+fn decode_port(port: DWORD) -> u16 {
+    u16::from_be(port as u16)
+}
+
+/// Retrieves one of the `*_OWNER_PID` tables, which consist of an entry count followed by rows.
+fn owner_table<R: Copy, F>(mut query: F) -> WinResult<Vec<R>>
+where
+    F: FnMut(*mut c_void, &mut DWORD) -> DWORD,
+{
+    let mut buffer: Vec<u32> = Vec::new();
+    loop {
+        let mut size = (buffer.len() * mem::size_of::<u32>()) as DWORD;
+        match query(buffer.as_mut_ptr() as _, &mut size) {
+            NO_ERROR => break,
+            ERROR_INSUFFICIENT_BUFFER => buffer.resize(size as usize / 4 + 1, 0),
+            code => return Err(Error::Os(io::Error::from_raw_os_error(code as _))),
+        }
+    }
+
+    unsafe {
+        let count = buffer.first().cloned().unwrap_or(0) as usize;
+        let rows = buffer.as_ptr().add(1) as *const R;
+        Ok((0..count)
+            .map(|i| ptr::read_unaligned(rows.add(i)))
+            .collect())
+    }
+}
@@ -0,0 +1,369 @@
+//! Bindings to the undocumented or partially documented `ntdll` APIs not covered by `winapi`.
+
+#![allow(non_snake_case, clippy::upper_case_acronyms)]
+
+use crate::{Error, WinResult};
+use std::{io, mem, slice};
+use winapi::{
+    shared::{
+        basetsd::{KAFFINITY, SIZE_T, ULONG_PTR},
+        minwindef::{ULONG, USHORT},
+        ntdef::{BOOLEAN, LIST_ENTRY, LONG, NTSTATUS, NT_SUCCESS, PVOID, UNICODE_STRING},
+        ntstatus::STATUS_INFO_LENGTH_MISMATCH,
+    },
+    um::winnt::HANDLE,
+};
+
+/// `PROCESSINFOCLASS::ProcessBasicInformation`
+pub const PROCESS_BASIC_INFORMATION_CLASS: ULONG = 0;
+/// `PROCESSINFOCLASS::ProcessWow64Information`
+pub const PROCESS_WOW64_INFORMATION: ULONG = 26;
+/// `PROCESSINFOCLASS::ProcessIoPriority`
+pub const PROCESS_IO_PRIORITY: ULONG = 33;
+
+/// `THREADINFOCLASS::ThreadBasicInformation`
+pub const THREAD_BASIC_INFORMATION_CLASS: ULONG = 0;
+
+/// `THREADINFOCLASS::ThreadSuspendCount`
+pub const THREAD_SUSPEND_COUNT: ULONG = 35;
+
+/// `SYSTEM_INFORMATION_CLASS::SystemProcessInformation`
+pub const SYSTEM_PROCESS_INFORMATION_CLASS: ULONG = 5;
+/// `SYSTEM_INFORMATION_CLASS::SystemExtendedHandleInformation`
+pub const SYSTEM_EXTENDED_HANDLE_INFORMATION_CLASS: ULONG = 64;
+
+/// `OBJECT_INFORMATION_CLASS::ObjectTypeInformation`
+pub const OBJECT_TYPE_INFORMATION_CLASS: ULONG = 2;
+
+/// `PROCESS_EXTENDED_BASIC_INFORMATION::IsFrozen`
+pub const PROCESS_FLAG_IS_FROZEN: ULONG = 0x10;
+
+/// `KTHREAD_STATE::Terminated`
+pub const THREAD_STATE_TERMINATED: ULONG = 4;
+/// `KTHREAD_STATE::Waiting`
+pub const THREAD_STATE_WAITING: ULONG = 5;
+/// `KWAIT_REASON::Suspended`
+pub const WAIT_REASON_SUSPENDED: ULONG = 5;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct CLIENT_ID {
+    pub UniqueProcess: HANDLE,
+    pub UniqueThread: HANDLE,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct PROCESS_BASIC_INFORMATION {
+    pub ExitStatus: NTSTATUS,
+    pub PebBaseAddress: PVOID,
+    pub AffinityMask: KAFFINITY,
+    pub BasePriority: LONG,
+    pub UniqueProcessId: ULONG_PTR,
+    pub InheritedFromUniqueProcessId: ULONG_PTR,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct PROCESS_EXTENDED_BASIC_INFORMATION {
+    pub Size: SIZE_T,
+    pub BasicInfo: PROCESS_BASIC_INFORMATION,
+    pub Flags: ULONG,
+}
+
+/// The leading fields of the process environment block.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct PEB {
+    pub InheritedAddressSpace: BOOLEAN,
+    pub ReadImageFileExecOptions: BOOLEAN,
+    pub BeingDebugged: BOOLEAN,
+    pub BitField: BOOLEAN,
+    pub Mutant: HANDLE,
+    pub ImageBaseAddress: PVOID,
+    pub Ldr: PVOID,
+    pub ProcessParameters: PVOID,
+}
+
+/// The leading fields of the loader data of a process.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct PEB_LDR_DATA {
+    pub Length: ULONG,
+    pub Initialized: BOOLEAN,
+    pub SsHandle: HANDLE,
+    pub InLoadOrderModuleList: LIST_ENTRY,
+    pub InMemoryOrderModuleList: LIST_ENTRY,
+    pub InInitializationOrderModuleList: LIST_ENTRY,
+}
+
+/// The leading fields of a loader module entry.
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct LDR_DATA_TABLE_ENTRY {
+    pub InLoadOrderLinks: LIST_ENTRY,
+    pub InMemoryOrderLinks: LIST_ENTRY,
+    pub InInitializationOrderLinks: LIST_ENTRY,
+    pub DllBase: PVOID,
+    pub EntryPoint: PVOID,
+    pub SizeOfImage: ULONG,
+    pub FullDllName: UNICODE_STRING,
+    pub BaseDllName: UNICODE_STRING,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct THREAD_BASIC_INFORMATION {
+    pub ExitStatus: NTSTATUS,
+    pub TebBaseAddress: PVOID,
+    pub ClientId: CLIENT_ID,
+    pub AffinityMask: KAFFINITY,
+    pub Priority: LONG,
+    pub BasePriority: LONG,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct SYSTEM_PROCESS_INFORMATION {
+    pub NextEntryOffset: ULONG,
+    pub NumberOfThreads: ULONG,
+    pub WorkingSetPrivateSize: i64,
+    pub HardFaultCount: ULONG,
+    pub NumberOfThreadsHighWatermark: ULONG,
+    pub CycleTime: u64,
+    pub CreateTime: i64,
+    pub UserTime: i64,
+    pub KernelTime: i64,
+    pub ImageName: UNICODE_STRING,
+    pub BasePriority: LONG,
+    pub UniqueProcessId: HANDLE,
+    pub InheritedFromUniqueProcessId: HANDLE,
+    pub HandleCount: ULONG,
+    pub SessionId: ULONG,
+    pub UniqueProcessKey: ULONG_PTR,
+    pub PeakVirtualSize: SIZE_T,
+    pub VirtualSize: SIZE_T,
+    pub PageFaultCount: ULONG,
+    pub PeakWorkingSetSize: SIZE_T,
+    pub WorkingSetSize: SIZE_T,
+    pub QuotaPeakPagedPoolUsage: SIZE_T,
+    pub QuotaPagedPoolUsage: SIZE_T,
+    pub QuotaPeakNonPagedPoolUsage: SIZE_T,
+    pub QuotaNonPagedPoolUsage: SIZE_T,
+    pub PagefileUsage: SIZE_T,
+    pub PeakPagefileUsage: SIZE_T,
+    pub PrivatePageCount: SIZE_T,
+    pub ReadOperationCount: i64,
+    pub WriteOperationCount: i64,
+    pub OtherOperationCount: i64,
+    pub ReadTransferCount: i64,
+    pub WriteTransferCount: i64,
+    pub OtherTransferCount: i64,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SYSTEM_THREAD_INFORMATION {
+    pub KernelTime: i64,
+    pub UserTime: i64,
+    pub CreateTime: i64,
+    pub WaitTime: ULONG,
+    pub StartAddress: PVOID,
+    pub ClientId: CLIENT_ID,
+    pub Priority: LONG,
+    pub BasePriority: LONG,
+    pub ContextSwitches: ULONG,
+    pub ThreadState: ULONG,
+    pub WaitReason: ULONG,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX {
+    pub Object: PVOID,
+    pub UniqueProcessId: ULONG_PTR,
+    pub HandleValue: ULONG_PTR,
+    pub GrantedAccess: ULONG,
+    pub CreatorBackTraceIndex: USHORT,
+    pub ObjectTypeIndex: USHORT,
+    pub HandleAttributes: ULONG,
+    pub Reserved: ULONG,
+}
+
+/// The header of `SYSTEM_HANDLE_INFORMATION_EX`, followed by the handle entries.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SYSTEM_HANDLE_INFORMATION_EX {
+    pub NumberOfHandles: ULONG_PTR,
+    pub Reserved: ULONG_PTR,
+}
+
+#[link(name = "ntdll")]
+extern "system" {
+    pub fn NtQueryInformationProcess(
+        ProcessHandle: HANDLE,
+        ProcessInformationClass: ULONG,
+        ProcessInformation: PVOID,
+        ProcessInformationLength: ULONG,
+        ReturnLength: *mut ULONG,
+    ) -> NTSTATUS;
+
+    pub fn NtSetInformationProcess(
+        ProcessHandle: HANDLE,
+        ProcessInformationClass: ULONG,
+        ProcessInformation: PVOID,
+        ProcessInformationLength: ULONG,
+    ) -> NTSTATUS;
+
+    pub fn NtQueryInformationThread(
+        ThreadHandle: HANDLE,
+        ThreadInformationClass: ULONG,
+        ThreadInformation: PVOID,
+        ThreadInformationLength: ULONG,
+        ReturnLength: *mut ULONG,
+    ) -> NTSTATUS;
+
+    pub fn NtQuerySystemInformation(
+        SystemInformationClass: ULONG,
+        SystemInformation: PVOID,
+        SystemInformationLength: ULONG,
+        ReturnLength: *mut ULONG,
+    ) -> NTSTATUS;
+
+    pub fn NtQueryObject(
+        Handle: HANDLE,
+        ObjectInformationClass: ULONG,
+        ObjectInformation: PVOID,
+        ObjectInformationLength: ULONG,
+        ReturnLength: *mut ULONG,
+    ) -> NTSTATUS;
+
+    pub fn RtlNtStatusToDosError(Status: NTSTATUS) -> ULONG;
+}
+
+/// Converts an `NTSTATUS` into a `WinResult`, translating failures to the equivalent Windows error.
+pub fn nt_result(status: NTSTATUS) -> WinResult {
+    if NT_SUCCESS(status) {
+        Ok(())
+    } else {
+        let code = unsafe { RtlNtStatusToDosError(status) };
+        Err(Error::Os(io::Error::from_raw_os_error(code as _)))
+    }
+}
+
+/// Queries a system information class whose size isn't known in advance, growing the buffer
+/// until it fits. The buffer is 8-byte aligned.
+fn query_system_information(class: ULONG) -> WinResult<Vec<u64>> {
+    let mut buffer: Vec<u64> = vec![0; 0x10000];
+    loop {
+        let mut len = 0;
+        let status = unsafe {
+            NtQuerySystemInformation(
+                class,
+                buffer.as_mut_ptr() as _,
+                (buffer.len() * mem::size_of::<u64>()) as _,
+                &mut len,
+            )
+        };
+        if status == STATUS_INFO_LENGTH_MISMATCH {
+            // Leave some room for entries added in the meantime.
+            let len = len as usize / mem::size_of::<u64>() + 0x1000;
+            buffer.resize(len.max(buffer.len() * 2), 0);
+        } else {
+            nt_result(status)?;
+            return Ok(buffer);
+        }
+    }
+}
+
+/// A snapshot of the processes and threads of the system.
+pub struct SystemProcesses {
+    buffer: Vec<u64>,
+}
+
+impl SystemProcesses {
+    /// Takes a snapshot with `NtQuerySystemInformation(SystemProcessInformation)`.
+    pub fn snapshot() -> WinResult<SystemProcesses> {
+        Ok(SystemProcesses {
+            buffer: query_system_information(SYSTEM_PROCESS_INFORMATION_CLASS)?,
+        })
+    }
+
+    /// Returns an iterator over the processes and their threads.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&SYSTEM_PROCESS_INFORMATION, &[SYSTEM_THREAD_INFORMATION])> {
+        let base = self.buffer.as_ptr() as *const u8;
+        let mut offset = Some(0);
+        std::iter::from_fn(move || unsafe {
+            let process = &*(base.add(offset?) as *const SYSTEM_PROCESS_INFORMATION);
+            let threads = slice::from_raw_parts(
+                (process as *const SYSTEM_PROCESS_INFORMATION).add(1)
+                    as *const SYSTEM_THREAD_INFORMATION,
+                process.NumberOfThreads as usize,
+            );
+            offset = match process.NextEntryOffset {
+                0 => None,
+                next => Some(offset? + next as usize),
+            };
+            Some((process, threads))
+        })
+    }
+}
+
+/// A snapshot of the open handles of the system.
+pub struct SystemHandles {
+    buffer: Vec<u64>,
+}
+
+impl SystemHandles {
+    /// Takes a snapshot with `NtQuerySystemInformation(SystemExtendedHandleInformation)`.
+    pub fn snapshot() -> WinResult<SystemHandles> {
+        Ok(SystemHandles {
+            buffer: query_system_information(SYSTEM_EXTENDED_HANDLE_INFORMATION_CLASS)?,
+        })
+    }
+
+    /// Returns the handles of all processes.
+    pub fn handles(&self) -> &[SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX] {
+        unsafe {
+            let header = &*(self.buffer.as_ptr() as *const SYSTEM_HANDLE_INFORMATION_EX);
+            slice::from_raw_parts(
+                (header as *const SYSTEM_HANDLE_INFORMATION_EX).add(1)
+                    as *const SYSTEM_HANDLE_TABLE_ENTRY_INFO_EX,
+                header.NumberOfHandles,
+            )
+        }
+    }
+}
+
+/// Returns the name of the type of the object, such as `File` or `Event`, with
+/// `NtQueryObject(ObjectTypeInformation)`.
+pub fn object_type_name(handle: HANDLE) -> WinResult<String> {
+    // `OBJECT_TYPE_INFORMATION` starts with the name, whose buffer follows the structure.
+    let mut buffer: Vec<u64> = vec![0; 0x100];
+    loop {
+        let mut len = 0;
+        let status = unsafe {
+            NtQueryObject(
+                handle,
+                OBJECT_TYPE_INFORMATION_CLASS,
+                buffer.as_mut_ptr() as _,
+                (buffer.len() * mem::size_of::<u64>()) as _,
+                &mut len,
+            )
+        };
+        if status == STATUS_INFO_LENGTH_MISMATCH
+            && len as usize > buffer.len() * mem::size_of::<u64>()
+        {
+            buffer.resize(len as usize / mem::size_of::<u64>() + 1, 0);
+        } else {
+            nt_result(status)?;
+            return unsafe {
+                let name = &*(buffer.as_ptr() as *const UNICODE_STRING);
+                let name = slice::from_raw_parts(name.Buffer, name.Length as usize / 2);
+                Ok(String::from_utf16_lossy(name))
+            };
+        }
+    }
+}
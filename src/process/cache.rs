@@ -0,0 +1,100 @@
+use super::{Access, Process, ProcessIdentity};
+use crate::WinResult;
+use std::collections::{hash_map::Entry, HashMap};
+
+/// A cache of open process handles, for tools that repeatedly operate on the same processes.
+///
+/// Handles are keyed by process id, and are only handed out while their process is still
+/// running. An open handle keeps its process id from being reused, and each entry remembers the
+/// identity of the process it was opened for, so a handle is never confused with a newer process
+/// that was given the same id.
+#[derive(Debug, Default)]
+pub struct ProcessCache {
+    entries: HashMap<u32, CachedProcess>,
+}
+
+#[derive(Debug)]
+struct CachedProcess {
+    identity: ProcessIdentity,
+    access: Access,
+    process: Process,
+}
+
+impl ProcessCache {
+    /// Creates an empty cache.
+    pub fn new() -> ProcessCache {
+        ProcessCache::default()
+    }
+
+    /// Returns the cached handle to the process with the specified id, or opens a new one.
+    ///
+    /// A cached handle is replaced if its process exited or if it lacks some of the requested
+    /// access rights. The `PROCESS_QUERY_LIMITED_INFORMATION` access right is always requested,
+    /// as it's needed to validate the handle.
+    pub fn get_or_open(&mut self, id: u32, access: Access) -> WinResult<&Process> {
+        let access = access | Access::PROCESS_QUERY_LIMITED_INFORMATION;
+        match self.entries.entry(id) {
+            Entry::Occupied(mut entry) => {
+                if !entry.get().is_valid_for(access) {
+                    // Open with the union of both rights, so callers alternating between access
+                    // rights don't keep replacing the handle.
+                    let access = access | entry.get().access;
+                    entry.insert(CachedProcess::open(id, access)?);
+                }
+                Ok(&entry.into_mut().process)
+            }
+            Entry::Vacant(entry) => Ok(&entry.insert(CachedProcess::open(id, access)?).process),
+        }
+    }
+
+    /// Returns the identity of the cached process with the specified id, if any.
+    pub fn identity(&self, id: u32) -> Option<ProcessIdentity> {
+        self.entries.get(&id).map(|entry| entry.identity)
+    }
+
+    /// Removes the handle to the process with the specified id from the cache, and returns it.
+    pub fn remove(&mut self, id: u32) -> Option<Process> {
+        self.entries.remove(&id).map(|entry| entry.process)
+    }
+
+    /// Closes the handles of the processes that exited.
+    pub fn prune(&mut self) {
+        self.entries.retain(|_, entry| entry.process.is_running());
+    }
+
+    /// Closes all the cached handles.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Returns the number of cached handles.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache holds no handles.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl CachedProcess {
+    fn open(id: u32, access: Access) -> WinResult<CachedProcess> {
+        let process = Process::from_id_with_access(id, access)?;
+        Ok(CachedProcess {
+            identity: process.identity()?,
+            access,
+            process,
+        })
+    }
+
+    fn is_valid_for(&self, access: Access) -> bool {
+        self.access.contains(access)
+            && self.process.is_running()
+            && self
+                .process
+                .identity()
+                .map(|identity| identity == self.identity)
+                .unwrap_or(false)
+    }
+}
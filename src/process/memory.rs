@@ -0,0 +1,648 @@
+use crate::{Error, Process, WinResult};
+use bitflags::bitflags;
+use std::{
+    ffi::OsString,
+    io,
+    mem,
+    os::windows::{ffi::OsStringExt, io::AsRawHandle},
+    ptr::null_mut,
+    slice,
+};
+use winapi::{
+    shared::winerror::{ERROR_INVALID_PARAMETER, ERROR_PARTIAL_COPY},
+    um::{
+        memoryapi::{
+            GetProcessWorkingSetSizeEx,
+            ReadProcessMemory,
+            VirtualAllocEx,
+            VirtualFreeEx,
+            VirtualProtectEx,
+            VirtualQueryEx,
+            WriteProcessMemory,
+        },
+        processthreadsapi::FlushInstructionCache,
+        psapi::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS_EX},
+        winnt::{
+            self,
+            MEMORY_BASIC_INFORMATION,
+            MEM_COMMIT,
+            MEM_RELEASE,
+            MEM_RESERVE,
+            PAGE_EXECUTE_READWRITE,
+            PAGE_GUARD,
+            PAGE_NOACCESS,
+            QUOTA_LIMITS_HARDWS_MAX_ENABLE,
+            QUOTA_LIMITS_HARDWS_MIN_ENABLE,
+        },
+    },
+};
+
+/// The size of the smallest pages, which bounds the reads of `Process::read_multi_wide_string`.
+const PAGE_SIZE: usize = 0x1000;
+
+/// The largest piece of a region that `Process::for_each_region` reads at once.
+const REGION_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+impl Process {
+    /// Reads memory of the process at the specified address into a buffer. On success, returns
+    /// the number of bytes read.
+    ///
+    /// If the range runs into an unreadable page, the bytes read before it are still reported,
+    /// and an error is only returned if none could be read. Reading into an empty buffer does
+    /// nothing and returns `0`.
+    ///
+    /// The handle must have the `PROCESS_VM_READ` access right.
+    pub fn read_memory(&self, address: usize, buf: &mut [u8]) -> WinResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        unsafe {
+            let mut read = 0;
+            let ret = ReadProcessMemory(
+                self.as_raw_handle() as winnt::HANDLE,
+                address as _,
+                buf.as_mut_ptr() as _,
+                buf.len(),
+                &mut read,
+            );
+            if ret == 0 && read == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(read)
+            }
+        }
+    }
+
+    /// Writes a buffer into the memory of the process at the specified address. On success,
+    /// returns the number of bytes written.
+    ///
+    /// The protection of the pages is never changed, so writing to read-only memory fails. If the
+    /// range runs into such a page, the bytes written before it are still reported, and an error
+    /// is only returned if none could be written. Writing an empty buffer does nothing and returns
+    /// `0`.
+    ///
+    /// The handle must have the `PROCESS_VM_WRITE` and `PROCESS_VM_OPERATION` access rights.
+    pub fn write_memory(&self, address: usize, buf: &[u8]) -> WinResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        unsafe {
+            let mut written = 0;
+            let ret = WriteProcessMemory(
+                self.as_raw_handle() as winnt::HANDLE,
+                address as _,
+                buf.as_ptr() as _,
+                buf.len(),
+                &mut written,
+            );
+            if ret == 0 && written == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(written)
+            }
+        }
+    }
+
+    /// Changes the protection of the pages in the range, returning the previous protection of the
+    /// first page, which can be passed back to restore it.
+    ///
+    /// Every page overlapping the range is changed, and they must all belong to the same
+    /// allocation.
+    ///
+    /// The handle must have the `PROCESS_VM_OPERATION` access right.
+    pub fn protect(&self, address: usize, size: usize, new: Protection) -> WinResult<Protection> {
+        // Keep modifier bits that `Protection` doesn't name, so restoring them is lossless.
+        self.protect_raw(address, size, new.bits())
+            .map(|old| unsafe { Protection::from_bits_unchecked(old) })
+    }
+
+    /// Changes the protection of the pages in the range, returning the previous protection of the
+    /// first page.
+    pub(crate) fn protect_raw(&self, address: usize, size: usize, protect: u32) -> WinResult<u32> {
+        unsafe {
+            let mut old = 0;
+            let ret = VirtualProtectEx(
+                self.as_raw_handle() as winnt::HANDLE,
+                address as _,
+                size,
+                protect,
+                &mut old,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(old)
+            }
+        }
+    }
+
+    /// Overwrites code of the process, temporarily making its pages writable and flushing the
+    /// instruction cache afterwards.
+    fn patch_code(&self, address: usize, bytes: &[u8]) -> WinResult {
+        let old = self.protect_raw(address, bytes.len(), PAGE_EXECUTE_READWRITE)?;
+        let written = self.write_memory(address, bytes);
+        self.protect_raw(address, bytes.len(), old)?;
+        if written? != bytes.len() {
+            return Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_PARTIAL_COPY as _,
+            )));
+        }
+        unsafe {
+            let ret = FlushInstructionCache(
+                self.as_raw_handle() as winnt::HANDLE,
+                address as _,
+                bytes.len(),
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads a list of nul-terminated UTF-16 strings ending with an empty string (a double nul),
+    /// such as an environment block, at the specified address.
+    ///
+    /// Reads at most `max_bytes` bytes. If no terminator is found by then, the strings read so far
+    /// are returned, including the unterminated last one. The list is read in chunks that don't
+    /// cross page boundaries, so it may end right before an unreadable page.
+    ///
+    /// The handle must have the `PROCESS_VM_READ` access right.
+    pub fn read_multi_wide_string(
+        &self,
+        address: usize,
+        max_bytes: usize,
+    ) -> WinResult<Vec<String>> {
+        let mut strings = Vec::new();
+        let mut current = Vec::new();
+        let mut chunk = [0u16; PAGE_SIZE / 2];
+        let end = address.saturating_add(max_bytes & !1);
+        let mut next = address;
+        while next < end {
+            let page_end = (next / PAGE_SIZE + 1).saturating_mul(PAGE_SIZE);
+            let len = (page_end.min(end) - next) / 2;
+            if len == 0 {
+                // An odd address leaves a single byte before the page boundary.
+                break;
+            }
+            let units = &mut chunk[..len];
+            unsafe {
+                let bytes = slice::from_raw_parts_mut(units.as_mut_ptr() as *mut u8, len * 2);
+                self.read_exact(next, bytes)?;
+            }
+            for &unit in units.iter() {
+                if unit != 0 {
+                    current.push(unit);
+                } else if current.is_empty() {
+                    return Ok(strings);
+                } else {
+                    strings.push(wide_to_string(&current));
+                    current.clear();
+                }
+            }
+            next += len * 2;
+        }
+        if !current.is_empty() {
+            strings.push(wide_to_string(&current));
+        }
+        Ok(strings)
+    }
+
+    /// Sets a software breakpoint by replacing the byte at the address with an `int3`
+    /// instruction (`0xCC`). On success, returns the original byte, which must be passed to
+    /// `clear_software_breakpoint` to remove the breakpoint.
+    ///
+    /// The protection of the page is restored after writing, and the instruction cache is flushed.
+    ///
+    /// The handle must have the `PROCESS_VM_READ`, `PROCESS_VM_WRITE` and `PROCESS_VM_OPERATION`
+    /// access rights.
+    pub fn set_software_breakpoint(&self, address: usize) -> WinResult<u8> {
+        let original: u8 = self.read_value(address)?;
+        self.patch_code(address, &[0xCC])?;
+        Ok(original)
+    }
+
+    /// Removes a software breakpoint set with `set_software_breakpoint`, restoring the original
+    /// byte it returned.
+    ///
+    /// The handle must have the `PROCESS_VM_WRITE` and `PROCESS_VM_OPERATION` access rights.
+    pub fn clear_software_breakpoint(&self, address: usize, original: u8) -> WinResult {
+        self.patch_code(address, &[original])
+    }
+
+    /// Returns the minimum and maximum working set sizes of the process, and whether they are
+    /// enforced as hard limits.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn working_set_size(&self) -> WinResult<WorkingSetLimits> {
+        unsafe {
+            let mut minimum = 0;
+            let mut maximum = 0;
+            let mut flags = 0;
+            let ret = GetProcessWorkingSetSizeEx(
+                self.as_raw_handle() as winnt::HANDLE,
+                &mut minimum,
+                &mut maximum,
+                &mut flags,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(WorkingSetLimits {
+                    minimum,
+                    maximum,
+                    hard_minimum: flags & QUOTA_LIMITS_HARDWS_MIN_ENABLE != 0,
+                    hard_maximum: flags & QUOTA_LIMITS_HARDWS_MAX_ENABLE != 0,
+                })
+            }
+        }
+    }
+
+    /// Returns the memory usage of the process.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn memory_info(&self) -> WinResult<MemoryCounters> {
+        unsafe {
+            let mut counters: PROCESS_MEMORY_COUNTERS_EX = mem::zeroed();
+            counters.cb = mem::size_of::<PROCESS_MEMORY_COUNTERS_EX>() as _;
+            let ret = GetProcessMemoryInfo(
+                self.as_raw_handle() as winnt::HANDLE,
+                &mut counters as *mut _ as _,
+                counters.cb,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(counters.into())
+            }
+        }
+    }
+
+    /// Fills the whole buffer from the process's memory, failing on a short read.
+    pub(crate) fn read_exact(&self, address: usize, buf: &mut [u8]) -> WinResult {
+        if self.read_memory(address, buf)? == buf.len() {
+            Ok(())
+        } else {
+            Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_PARTIAL_COPY as _,
+            )))
+        }
+    }
+
+    /// Reads a plain-old-data value from the process's memory.
+    pub(crate) fn read_value<T: Copy>(&self, address: usize) -> WinResult<T> {
+        unsafe {
+            let mut value: T = mem::zeroed();
+            let buf =
+                slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, mem::size_of::<T>());
+            self.read_exact(address, buf)?;
+            Ok(value)
+        }
+    }
+
+    /// Reads a value of type `T` from the memory of the process at the specified address.
+    ///
+    /// Fails with `ERROR_PARTIAL_COPY` if fewer than `size_of::<T>()` bytes could be read.
+    ///
+    /// **The bytes are reinterpreted as a `T` as is**, so `T` must be a plain-old-data type with
+    /// a defined layout, such as a `#[repr(C)]` struct of integers, for which every bit pattern is
+    /// valid. Pointers, references, `bool`s and enums must not be read this way.
+    ///
+    /// The handle must have the `PROCESS_VM_READ` access right.
+    pub fn read_struct<T: Copy>(&self, address: usize) -> WinResult<T> {
+        self.read_value(address)
+    }
+
+    /// Writes the bytes of a value into the memory of the process at the specified address.
+    ///
+    /// Fails with `ERROR_PARTIAL_COPY` if fewer than `size_of::<T>()` bytes could be written.
+    ///
+    /// **The bytes of the value are copied as is**, so `T` must be a plain-old-data type with a
+    /// defined layout, such as a `#[repr(C)]` struct of integers. Padding bytes are written with
+    /// unspecified values.
+    ///
+    /// The handle must have the `PROCESS_VM_WRITE` and `PROCESS_VM_OPERATION` access rights.
+    pub fn write_struct<T: Copy>(&self, address: usize, value: &T) -> WinResult {
+        let buf =
+            unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+        if self.write_memory(address, buf)? == buf.len() {
+            Ok(())
+        } else {
+            Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_PARTIAL_COPY as _,
+            )))
+        }
+    }
+
+    /// Reserves and commits a region of memory in the process, initialized to zero. Returns the
+    /// base address of the region.
+    ///
+    /// The size is rounded up to a multiple of the page size. A zero size fails with
+    /// `ERROR_INVALID_PARAMETER`.
+    ///
+    /// The handle must have the `PROCESS_VM_OPERATION` access right.
+    pub fn alloc(&self, size: usize, protection: Protection) -> WinResult<usize> {
+        if size == 0 {
+            return Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_INVALID_PARAMETER as _,
+            )));
+        }
+        unsafe {
+            let address = VirtualAllocEx(
+                self.as_raw_handle() as winnt::HANDLE,
+                null_mut(),
+                size,
+                MEM_COMMIT | MEM_RESERVE,
+                protection.bits(),
+            );
+            if address.is_null() {
+                Err(Error::last_os_error())
+            } else {
+                Ok(address as usize)
+            }
+        }
+    }
+
+    /// Like `alloc`, but returns a guard that frees the region when dropped, so it isn't leaked
+    /// if an error happens before it's handed over to the process.
+    ///
+    /// The handle must have the `PROCESS_VM_OPERATION` access right.
+    pub fn alloc_guarded(&self, size: usize, protection: Protection) -> WinResult<RemoteAlloc<'_>> {
+        Ok(RemoteAlloc {
+            process: self,
+            address: Some(self.alloc(size, protection)?),
+        })
+    }
+
+    /// Releases a whole region of memory allocated in the process by `alloc`.
+    ///
+    /// `address` must be the base address returned by `alloc`.
+    ///
+    /// The handle must have the `PROCESS_VM_OPERATION` access right.
+    pub fn free(&self, address: usize) -> WinResult {
+        unsafe {
+            let ret = VirtualFreeEx(
+                self.as_raw_handle() as winnt::HANDLE,
+                address as _,
+                0,
+                MEM_RELEASE,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the region of pages with the same state, protection and type that contains the
+    /// specified address.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` access right.
+    pub fn query_memory(&self, address: usize) -> WinResult<MemoryRegion> {
+        unsafe {
+            let mut info: MEMORY_BASIC_INFORMATION = mem::zeroed();
+            let ret = VirtualQueryEx(
+                self.as_raw_handle() as winnt::HANDLE,
+                address as _,
+                &mut info,
+                mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(info.into())
+            }
+        }
+    }
+
+    /// Returns an iterator over the regions of the process's address space, in ascending order,
+    /// including free ones.
+    ///
+    /// The walk ends at the end of the user address space, where querying fails, or if the
+    /// process can't be queried at all.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` access right.
+    pub fn memory_regions(&self) -> impl Iterator<Item = MemoryRegion> + '_ {
+        let mut next = Some(0usize);
+        std::iter::from_fn(move || {
+            let region = self.query_memory(next?).ok()?;
+            next = region.base_address.checked_add(region.region_size);
+            Some(region)
+        })
+    }
+
+    /// Reads the committed regions of the process accepted by `filter`, and calls `f` with the
+    /// contents of each.
+    ///
+    /// The contents are read into a single reused buffer, so memory usage stays bounded even when
+    /// scanning a very large process. Regions larger than 16 MiB are split into pieces, and `f` is
+    /// called with a region describing each piece. Guard and no-access pages, as well as pieces
+    /// that fail to be read, are skipped. The walk stops early if `f` returns false.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` and `PROCESS_VM_READ` access rights.
+    pub fn for_each_region<P, F>(&self, mut filter: P, mut f: F) -> WinResult
+    where
+        P: FnMut(&MemoryRegion) -> bool,
+        F: FnMut(&MemoryRegion, &[u8]) -> bool,
+    {
+        let mut buffer = Vec::new();
+        for region in self.memory_regions() {
+            if region.state != MEM_COMMIT
+                || region.protect & (PAGE_GUARD | PAGE_NOACCESS) != 0
+                || !filter(&region)
+            {
+                continue;
+            }
+
+            let end = region.base_address + region.region_size;
+            let mut address = region.base_address;
+            while address < end {
+                let len = (end - address).min(REGION_CHUNK_SIZE);
+                buffer.resize(len, 0);
+                if let Ok(read) = self.read_memory(address, &mut buffer) {
+                    let piece = MemoryRegion {
+                        base_address: address,
+                        region_size: read,
+                        ..region
+                    };
+                    if !f(&piece, &buffer[..read]) {
+                        return Ok(());
+                    }
+                }
+                address += len;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn wide_to_string(units: &[u16]) -> String {
+    OsString::from_wide(units).to_string_lossy().into_owned()
+}
+
+/// A range of pages of a process's address space that share the same attributes.
+///
+/// Maps to a Windows [MEMORY_BASIC_INFORMATION][MEMORY_BASIC_INFORMATION].
+///
+/// [MEMORY_BASIC_INFORMATION]: https://docs.microsoft.com/en-us/windows/desktop/api/winnt/ns-winnt-_memory_basic_information
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MemoryRegion {
+    /// The base address of the region.
+    pub base_address: usize,
+    /// The base address of the allocation the region belongs to.
+    pub allocation_base: usize,
+    /// The size of the region, in bytes.
+    pub region_size: usize,
+    /// The state of the pages: `MEM_COMMIT`, `MEM_FREE` or `MEM_RESERVE`.
+    pub state: u32,
+    /// The access protection of the pages, such as `PAGE_READWRITE`.
+    pub protect: u32,
+    /// The type of the pages: `MEM_IMAGE`, `MEM_MAPPED` or `MEM_PRIVATE`.
+    pub type_: u32,
+}
+
+impl From<MEMORY_BASIC_INFORMATION> for MemoryRegion {
+    fn from(mbi: MEMORY_BASIC_INFORMATION) -> MemoryRegion {
+        MemoryRegion {
+            base_address: mbi.BaseAddress as usize,
+            allocation_base: mbi.AllocationBase as usize,
+            region_size: mbi.RegionSize,
+            state: mbi.State,
+            protect: mbi.Protect,
+            type_: mbi.Type,
+        }
+    }
+}
+
+/// A region of memory allocated in a process by `Process::alloc_guarded`.
+///
+/// Frees the region when dropped, unless it was released with `leak`.
+#[derive(Debug)]
+pub struct RemoteAlloc<'a> {
+    process: &'a Process,
+    address: Option<usize>,
+}
+
+impl<'a> RemoteAlloc<'a> {
+    /// Returns the base address of the region in the process.
+    pub fn address(&self) -> usize {
+        self.address.unwrap()
+    }
+
+    /// Frees the region now, reporting failures.
+    pub fn free(mut self) -> WinResult {
+        self.process.free(self.address.take().unwrap())
+    }
+
+    /// Gives up ownership of the region, which won't be freed, and returns its base address.
+    pub fn leak(mut self) -> usize {
+        self.address.take().unwrap()
+    }
+}
+
+impl<'a> Drop for RemoteAlloc<'a> {
+    fn drop(&mut self) {
+        if let Some(address) = self.address {
+            let _ = self.process.free(address);
+        }
+    }
+}
+
+/// The memory usage of a process, as returned by `Process::memory_info`.
+///
+/// Maps to a Windows [PROCESS_MEMORY_COUNTERS_EX][PROCESS_MEMORY_COUNTERS_EX]. Sizes are in bytes.
+///
+/// [PROCESS_MEMORY_COUNTERS_EX]: https://docs.microsoft.com/en-us/windows/desktop/api/psapi/ns-psapi-_process_memory_counters_ex
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct MemoryCounters {
+    /// The number of page faults.
+    pub page_fault_count: u32,
+    /// The peak working set size.
+    pub peak_working_set_size: usize,
+    /// The current working set size.
+    pub working_set_size: usize,
+    /// The peak paged pool usage.
+    pub quota_peak_paged_pool_usage: usize,
+    /// The current paged pool usage.
+    pub quota_paged_pool_usage: usize,
+    /// The peak nonpaged pool usage.
+    pub quota_peak_non_paged_pool_usage: usize,
+    /// The current nonpaged pool usage.
+    pub quota_non_paged_pool_usage: usize,
+    /// The commit charge of the process, which is the same as `private_usage`.
+    pub pagefile_usage: usize,
+    /// The peak commit charge of the process.
+    pub peak_pagefile_usage: usize,
+    /// The memory the process has committed that can't be shared with other processes.
+    pub private_usage: usize,
+}
+
+impl From<PROCESS_MEMORY_COUNTERS_EX> for MemoryCounters {
+    fn from(pmc: PROCESS_MEMORY_COUNTERS_EX) -> MemoryCounters {
+        MemoryCounters {
+            page_fault_count: pmc.PageFaultCount,
+            peak_working_set_size: pmc.PeakWorkingSetSize,
+            working_set_size: pmc.WorkingSetSize,
+            quota_peak_paged_pool_usage: pmc.QuotaPeakPagedPoolUsage,
+            quota_paged_pool_usage: pmc.QuotaPagedPoolUsage,
+            quota_peak_non_paged_pool_usage: pmc.QuotaPeakNonPagedPoolUsage,
+            quota_non_paged_pool_usage: pmc.QuotaNonPagedPoolUsage,
+            pagefile_usage: pmc.PagefileUsage,
+            peak_pagefile_usage: pmc.PeakPagefileUsage,
+            private_usage: pmc.PrivateUsage,
+        }
+    }
+}
+
+/// The working set size limits of a process.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct WorkingSetLimits {
+    /// The minimum working set size, in bytes.
+    pub minimum: usize,
+    /// The maximum working set size, in bytes.
+    pub maximum: usize,
+    /// Whether the working set never shrinks below the minimum. Otherwise, the minimum is only
+    /// honored when memory is plentiful.
+    pub hard_minimum: bool,
+    /// Whether the working set never grows beyond the maximum. Otherwise, the maximum is only
+    /// honored when memory is scarce.
+    pub hard_maximum: bool,
+}
+
+bitflags! {
+    /// Memory protection flags, as used by `Process::alloc`.
+    ///
+    /// Exactly one of the access flags must be set, optionally combined with `GUARD`, `NOCACHE`
+    /// or `WRITECOMBINE`. See [Memory Protection Constants](https://docs.microsoft.com/en-us/windows/desktop/Memory/memory-protection-constants).
+    pub struct Protection: u32 {
+        /// Disables all access to the pages.
+        const NOACCESS = winnt::PAGE_NOACCESS;
+        /// Enables read-only access to the pages.
+        const READONLY = winnt::PAGE_READONLY;
+        /// Enables read-only or read/write access to the pages.
+        const READWRITE = winnt::PAGE_READWRITE;
+        /// Enables read-only or copy-on-write access to a mapped view of a file mapping object.
+        const WRITECOPY = winnt::PAGE_WRITECOPY;
+        /// Enables execute access to the pages.
+        const EXECUTE = winnt::PAGE_EXECUTE;
+        /// Enables execute or read-only access to the pages.
+        const EXECUTE_READ = winnt::PAGE_EXECUTE_READ;
+        /// Enables execute, read-only, or read/write access to the pages.
+        const EXECUTE_READWRITE = winnt::PAGE_EXECUTE_READWRITE;
+        /// Enables execute, read-only, or copy-on-write access to a mapped view of a file mapping
+        /// object.
+        const EXECUTE_WRITECOPY = winnt::PAGE_EXECUTE_WRITECOPY;
+        /// The pages are guard pages, which raise a one-shot exception when first accessed.
+        const GUARD = winnt::PAGE_GUARD;
+        /// The pages are not cached.
+        const NOCACHE = winnt::PAGE_NOCACHE;
+        /// The pages are write-combined.
+        const WRITECOMBINE = winnt::PAGE_WRITECOMBINE;
+        /// The pages are invalid targets for Control Flow Guard.
+        const TARGETS_INVALID = winnt::PAGE_TARGETS_INVALID;
+    }
+}
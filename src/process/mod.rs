@@ -1,70 +1,164 @@
+pub use self::{
+    cache::ProcessCache,
+    memory::{MemoryCounters, MemoryRegion, Protection, RemoteAlloc, WorkingSetLimits},
+    module::{
+        module_diff,
+        DebugInfo,
+        ImportedFunction,
+        ImportedModule,
+        Module,
+        ModuleDiff,
+        ModuleEntry,
+        ModuleInfo,
+        OrderedModule,
+        Section,
+    },
+    pe::PeHeaders,
+    peb::{HeapInfo, ProcessParameters, StartupInfo},
+    thread::{
+        all_threads_with_owner,
+        thread_count,
+        threads_by_process,
+        ContextFlags,
+        PriorityLevel,
+        Thread,
+        ThreadAccess,
+        ThreadEntry,
+        ThreadState,
+        ThreadTimes,
+    },
+    token::{ImpersonationGuard, IntegrityLevel, Token},
+};
 use self::{
     module::ModuleEntryIter,
+    pe::{Export, RemoteImage},
     thread::{ThreadIdIter, ThreadIter},
 };
-pub use self::{
-    module::{Module, ModuleEntry, ModuleInfo},
-    thread::{PriorityLevel, Thread},
-};
-use crate::{Error, Handle, WinResult};
+use crate::{dynamic, ntdll, user32, CoreClass, CpuSet, Error, GroupAffinity, Handle, WinResult};
 use bitflags::bitflags;
 use std::{
-    ffi::{OsStr, OsString},
+    collections::{hash_map::Entry, HashMap, HashSet},
+    ffi::{CString, OsStr, OsString},
+    io,
     mem,
     ops::Deref,
     os::windows::{
         io::{AsRawHandle, FromRawHandle, IntoRawHandle, RawHandle},
         prelude::*,
     },
-    path::PathBuf,
+    path::{Path, PathBuf},
+    ptr::null_mut,
+    slice,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use widestring::WideCString;
 use winapi::{
+    ctypes::c_void,
     shared::{
         basetsd::DWORD_PTR,
-        minwindef::{DWORD, HMODULE, MAX_PATH},
+        minwindef::{DWORD, FILETIME, HMODULE, LPVOID, MAX_PATH},
+        winerror::{
+            APPMODEL_ERROR_NO_PACKAGE,
+            ERROR_INSUFFICIENT_BUFFER,
+            ERROR_INVALID_PARAMETER,
+            ERROR_MOD_NOT_FOUND,
+            ERROR_NOT_SUPPORTED,
+            ERROR_PARTIAL_COPY,
+            WAIT_TIMEOUT,
+        },
     },
     um::{
-        handleapi::INVALID_HANDLE_VALUE,
-        libloaderapi::GetModuleHandleW,
+        errhandlingapi::{GetLastError, SetLastError},
+        handleapi::{DuplicateHandle, INVALID_HANDLE_VALUE},
+        libloaderapi::{GetModuleHandleW, GetProcAddress},
+        minwinbase::STILL_ACTIVE,
         processthreadsapi::{
+            CreateRemoteThreadEx,
+            DeleteProcThreadAttributeList,
             GetCurrentProcess,
+            GetCurrentProcessId,
+            GetCurrentThreadId,
             GetExitCodeProcess,
             GetPriorityClass,
+            GetProcessHandleCount,
             GetProcessId,
+            GetProcessTimes,
+            InitializeProcThreadAttributeList,
             OpenProcess,
+            OpenProcessToken,
             SetPriorityClass,
             TerminateProcess,
+            UpdateProcThreadAttribute,
+            LPPROC_THREAD_ATTRIBUTE_LIST,
         },
+        processtopologyapi::GetProcessGroupAffinity,
         psapi::{EnumProcessModulesEx, LIST_MODULES_ALL},
+        synchapi::{WaitForMultipleObjects, WaitForSingleObject},
+        sysinfoapi::{GetNativeSystemInfo, GetSystemTimeAsFileTime, SYSTEM_INFO},
         tlhelp32::{
             CreateToolhelp32Snapshot,
-            Process32Next,
-            PROCESSENTRY32,
+            Process32FirstW,
+            Process32NextW,
+            PROCESSENTRY32W,
             TH32CS_SNAPMODULE,
             TH32CS_SNAPMODULE32,
             TH32CS_SNAPPROCESS,
             TH32CS_SNAPTHREAD,
         },
         winbase::{
+            self,
+            GetActiveProcessorCount,
             GetProcessAffinityMask,
+            GetProcessIoCounters,
             QueryFullProcessImageNameW,
             SetProcessAffinityMask,
             ABOVE_NORMAL_PRIORITY_CLASS,
             BELOW_NORMAL_PRIORITY_CLASS,
             HIGH_PRIORITY_CLASS,
             IDLE_PRIORITY_CLASS,
+            INFINITE,
             NORMAL_PRIORITY_CLASS,
             PROCESS_MODE_BACKGROUND_BEGIN,
             PROCESS_MODE_BACKGROUND_END,
             REALTIME_PRIORITY_CLASS,
+            WAIT_FAILED,
+            WAIT_OBJECT_0,
+        },
+        winnt::{
+            self,
+            GROUP_AFFINITY,
+            IMAGE_FILE_MACHINE_AMD64,
+            IMAGE_FILE_MACHINE_ARM64,
+            IMAGE_FILE_MACHINE_I386,
+            IMAGE_FILE_MACHINE_UNKNOWN,
+            IO_COUNTERS,
+            MAXIMUM_WAIT_OBJECTS,
+            PROCESSOR_ARCHITECTURE_AMD64,
+            PROCESSOR_ARCHITECTURE_ARM64,
+            PROCESSOR_ARCHITECTURE_INTEL,
+            TOKEN_QUERY,
+            WCHAR,
         },
-        winnt::{self, PROCESS_ALL_ACCESS, WCHAR},
+        wow64apiset::IsWow64Process,
     },
 };
 
+mod cache;
+mod memory;
 mod module;
+mod pe;
+mod peb;
 mod thread;
+mod token;
+
+/// The most export forwarders followed when resolving a function, in case they form a cycle.
+const MAX_FORWARDER_DEPTH: u32 = 16;
+/// The maximum length of an extended-length path, in UTF-16 code units.
+const MAX_LONG_PATH: usize = 32_767;
+/// The PID of the System Idle Process.
+const IDLE_PROCESS_ID: u32 = 0;
+/// The PID of the System process.
+const SYSTEM_PROCESS_ID: u32 = 4;
 
 /// A handle to a running process.
 #[derive(Debug)]
@@ -74,25 +168,25 @@ pub struct Process {
 
 impl Process {
     /// Creates a process handle from a PID. Requests all access permissions.
+    ///
+    /// Fails with `Error::ProtectedSystemProcess` for the System Idle Process and the System
+    /// process.
     pub fn from_id(id: u32) -> WinResult<Process> {
-        unsafe {
-            let handle = OpenProcess(PROCESS_ALL_ACCESS, 0, id);
-            if handle.is_null() {
-                Err(Error::last_os_error())
-            } else {
-                Ok(Process {
-                    handle: Handle::new(handle),
-                })
-            }
-        }
+        Process::from_id_with_access(id, Access::PROCESS_ALL_ACCESS)
     }
 
     /// Creates a process handle from a PID. Requests the specified access permissions.
+    ///
+    /// Fails with `Error::ProtectedSystemProcess` if the System Idle Process or the System process
+    /// can't be opened with the requested access.
     pub fn from_id_with_access(id: u32, access: Access) -> WinResult<Process> {
         unsafe {
             let handle = OpenProcess(access.bits, 0, id);
             if handle.is_null() {
-                Err(Error::last_os_error())
+                match id {
+                    IDLE_PROCESS_ID | SYSTEM_PROCESS_ID => Err(Error::ProtectedSystemProcess(id)),
+                    _ => Err(Error::last_os_error()),
+                }
             } else {
                 Ok(Process {
                     handle: Handle::new(handle),
@@ -101,6 +195,12 @@ impl Process {
         }
     }
 
+    /// Returns true if the process with the specified PID can be opened with the specified access
+    /// permissions, without keeping a handle to it.
+    pub fn can_open(id: u32, access: Access) -> bool {
+        Process::from_id_with_access(id, access).is_ok()
+    }
+
     /// Creates a process handle from a name. Requests all access.
     pub fn from_name(name: &str) -> WinResult<Process> {
         Process::all()?
@@ -125,11 +225,29 @@ impl Process {
         unsafe { Process::from_handle(Handle::from_raw_handle(GetCurrentProcess() as RawHandle)) }
     }
 
+    /// Returns the id of the current process.
+    pub fn current_id() -> u32 {
+        unsafe { GetCurrentProcessId() }
+    }
+
     /// Returns a reference to the inner handle.
     pub fn handle(&self) -> &Handle {
         &self.handle
     }
 
+    /// Sets whether the process handle is inherited by child processes.
+    pub fn set_inheritable(&self, inheritable: bool) -> WinResult {
+        self.handle.set_inheritable(inheritable)
+    }
+
+    /// Sets whether the process handle is protected from being closed.
+    ///
+    /// Dropping a process whose handle is protected panics, so the protection must be removed
+    /// beforehand.
+    pub fn set_protected_from_close(&self, protected: bool) -> WinResult {
+        self.handle.set_protected_from_close(protected)
+    }
+
     /// Enumerates all running processes. Requests all access.
     pub fn all() -> WinResult<impl Iterator<Item = Process>> {
         unsafe {
@@ -140,6 +258,7 @@ impl Process {
                 Ok(ProcessIter {
                     snapshot: Handle::new(snap),
                     access: Access::PROCESS_ALL_ACCESS,
+                    started: false,
                 }
                 .filter_map(Result::ok))
             }
@@ -156,236 +275,1036 @@ impl Process {
                 Ok(ProcessIter {
                     snapshot: Handle::new(snap),
                     access,
+                    started: false,
                 }
                 .filter_map(Result::ok))
             }
         }
     }
 
+    /// Enumerates all running processes, sorted by the key computed by the closure. Requests the
+    /// `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    ///
+    /// Processes for which the closure returns `None`, for example because the underlying query
+    /// failed, are skipped. The key is computed once per process.
+    pub fn all_sorted_by<K, F>(mut key: F) -> WinResult<Vec<Process>>
+    where
+        K: Ord,
+        F: FnMut(&Process) -> Option<K>,
+    {
+        let mut keyed: Vec<(K, Process)> =
+            Process::all_with_access(Access::PROCESS_QUERY_LIMITED_INFORMATION)?
+                .filter_map(|p| key(&p).map(|k| (k, p)))
+                .collect();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(keyed.into_iter().map(|(_, p)| p).collect())
+    }
+
+    /// Enumerates all running processes except the current one. Requests all access.
+    pub fn all_except_self() -> WinResult<impl Iterator<Item = Process>> {
+        let current = Process::current_id();
+        Ok(Process::all()?.filter(move |p| p.id() != current))
+    }
+
+    /// Lists all running processes without opening them.
+    pub fn entries() -> WinResult<impl Iterator<Item = ProcessEntry>> {
+        unsafe {
+            let snap = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+            if snap == INVALID_HANDLE_VALUE {
+                Err(Error::last_os_error())
+            } else {
+                Ok(ProcessEntryIter {
+                    snapshot: Handle::new(snap),
+                    started: false,
+                })
+            }
+        }
+    }
+
+    /// Lists all running processes except the current one without opening them.
+    pub fn entries_except_self() -> WinResult<impl Iterator<Item = ProcessEntry>> {
+        let current = Process::current_id();
+        Ok(Process::entries()?.filter(move |e| e.id != current))
+    }
+
     /// Returns the process's id.
     pub fn id(&self) -> u32 {
         unsafe { GetProcessId(self.handle.as_raw_handle() as winnt::HANDLE) }
     }
 
+    /// Returns the id of the process that created the process.
+    ///
+    /// The id is read from a snapshot of the running processes. Fails with `Error::NoProcess` if
+    /// the process isn't in the snapshot because it has exited. Ids are reused, so the parent may
+    /// have exited and its id been given to an unrelated process.
+    pub fn parent_id(&self) -> WinResult<u32> {
+        let id = self.id();
+        Process::entries()?
+            .find(|e| e.id == id)
+            .map(|e| e.parent_id)
+            .ok_or_else(|| Error::NoProcess(id.to_string()))
+    }
+
+    /// Opens the process that created the process, with all access permissions.
+    ///
+    /// See `parent_id` for the caveats.
+    pub fn parent(&self) -> WinResult<Process> {
+        Process::from_id(self.parent_id()?)
+    }
+
     /// Returns true if the process is running.
     pub fn is_running(&self) -> bool {
         unsafe {
             let mut status = 0;
             GetExitCodeProcess(self.handle.as_raw_handle() as winnt::HANDLE, &mut status);
-            status == 259
+            status == STILL_ACTIVE
         }
     }
 
-    /// Returns the path of the executable of the process.
-    pub fn path(&self) -> WinResult<PathBuf> {
-        unsafe {
-            let mut size = MAX_PATH as u32;
-            let mut buffer: [WCHAR; MAX_PATH] = mem::zeroed();
-            let ret = QueryFullProcessImageNameW(
-                self.handle.as_raw_handle() as winnt::HANDLE,
-                0,
-                buffer.as_mut_ptr(),
-                &mut size,
-            );
-            if ret == 0 {
-                Err(Error::last_os_error())
-            } else {
-                Ok(OsString::from_wide(&buffer[0..size as usize]).into())
-            }
+    /// Returns the exit code of the process, or `None` if it's still running.
+    ///
+    /// A process that exited with the code `STILL_ACTIVE` (259) also returns `None`. Use
+    /// `wait_timeout` with a zero timeout to tell it apart from a running one.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn exit_code(&self) -> WinResult<Option<u32>> {
+        match self.exit_status()? {
+            STILL_ACTIVE => Ok(None),
+            code => Ok(Some(code)),
         }
     }
 
-    /// Returns the unqualified name of the executable of the process.
-    pub fn name(&self) -> WinResult<String> {
-        Ok(self
-            .path()?
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into_owned())
+    /// Waits until the process exits, and returns its exit code.
+    ///
+    /// The handle must have the `SYNCHRONIZE` and `PROCESS_QUERY_LIMITED_INFORMATION` access
+    /// rights.
+    pub fn wait(&self) -> WinResult<u32> {
+        self.wait_millis(INFINITE)?;
+        self.exit_status()
     }
 
-    /// Returns the priority class of the process.
+    /// Waits until the process exits or the timeout elapses. Returns the exit code of the
+    /// process, or `None` if it's still running.
     ///
-    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
-    /// access right.
-    pub fn priority(&self) -> WinResult<PriorityClass> {
+    /// A zero timeout checks whether the process has exited without blocking.
+    ///
+    /// The handle must have the `SYNCHRONIZE` and `PROCESS_QUERY_LIMITED_INFORMATION` access
+    /// rights.
+    pub fn wait_timeout(&self, timeout: Duration) -> WinResult<Option<u32>> {
+        if self.wait_millis(timeout_millis(timeout))? {
+            self.exit_status().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Waits on the process handle. Returns false if the timeout elapsed first.
+    fn wait_millis(&self, millis: DWORD) -> WinResult<bool> {
+        match unsafe { WaitForSingleObject(self.handle.as_raw_handle() as winnt::HANDLE, millis) } {
+            WAIT_FAILED => Err(Error::last_os_error()),
+            WAIT_TIMEOUT => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Returns the value of `GetExitCodeProcess`, which is `STILL_ACTIVE` while the process runs.
+    fn exit_status(&self) -> WinResult<u32> {
         unsafe {
-            let ret = GetPriorityClass(self.handle.as_raw_handle() as winnt::HANDLE);
+            let mut status = 0;
+            let ret = GetExitCodeProcess(self.handle.as_raw_handle() as winnt::HANDLE, &mut status);
             if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(PriorityClass::from_code(ret))
+                Ok(status)
             }
         }
     }
 
-    /// Sets the priority class of the process.
+    /// Returns the identity of the process, which, unlike its id, is never reused.
     ///
-    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
-    pub fn set_priority(&mut self, priority: PriorityClass) -> WinResult {
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn identity(&self) -> WinResult<ProcessIdentity> {
+        Ok(ProcessIdentity {
+            id: self.id(),
+            creation_time: self.raw_times()?[0],
+        })
+    }
+
+    /// Returns the creation and exit times of the process, and the CPU time it has used.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn times(&self) -> WinResult<ProcessTimes> {
+        let [creation, exit, kernel, user] = self.raw_times()?;
+        Ok(ProcessTimes {
+            creation: filetime_to_system_time(creation),
+            // The exit time is left zeroed while the process runs.
+            exit: if exit == 0 {
+                None
+            } else {
+                Some(filetime_to_system_time(exit))
+            },
+            kernel: filetime_to_duration(kernel),
+            user: filetime_to_duration(user),
+        })
+    }
+
+    /// Returns the creation, exit, kernel and user times from `GetProcessTimes`, as `FILETIME`
+    /// values.
+    fn raw_times(&self) -> WinResult<[u64; 4]> {
         unsafe {
-            let ret = SetPriorityClass(
+            let mut times: [FILETIME; 4] = mem::zeroed();
+            let [creation, exit, kernel, user] = &mut times;
+            let ret = GetProcessTimes(
                 self.handle.as_raw_handle() as winnt::HANDLE,
-                priority.as_code(),
+                creation,
+                exit,
+                kernel,
+                user,
             );
             if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(())
+                let mut raw = [0; 4];
+                for (raw, time) in raw.iter_mut().zip(times.iter()) {
+                    *raw = (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64;
+                }
+                Ok(raw)
             }
         }
     }
 
-    /// Begins background processing mode.
-    ///
-    /// **This can be initiated only if the handle refers to the current process.**
+    /// Returns true if the process looks like it was created suspended and never resumed, as
+    /// processes being hollowed are. Uses the default `NeverResumedHeuristic`.
     ///
-    /// The system lowers the resource scheduling priorities of the process (and its threads) so
-    /// that it can perform background work without significantly affecting activity in
-    /// the foreground.
+    /// This is a heuristic, not a guarantee. See `was_never_resumed_with` for details.
+    pub fn was_never_resumed(&self) -> WinResult<bool> {
+        self.was_never_resumed_with(&NeverResumedHeuristic::default())
+    }
+
+    /// Returns true if the process looks like it was created suspended and never resumed.
     ///
-    /// The function fails if the process is already in background processing mode.
+    /// The process matches if it was created less than `heuristic.max_age` ago, has at most
+    /// `heuristic.max_threads` threads, and all of them are suspended. A process created
+    /// suspended only has its initial thread, which the loader hasn't run yet. Debuggers and
+    /// legitimate launchers also create processes suspended for a short while, so this is a
+    /// heuristic, not a guarantee.
     ///
-    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
-    pub fn start_background_mode(&mut self) -> WinResult {
+    /// Requires Windows 8.1 or later. The handle must have the
+    /// `PROCESS_QUERY_LIMITED_INFORMATION` access right, and the threads of the process must be
+    /// openable with the `THREAD_QUERY_LIMITED_INFORMATION` access right.
+    pub fn was_never_resumed_with(&self, heuristic: &NeverResumedHeuristic) -> WinResult<bool> {
+        let now = unsafe {
+            let mut now: FILETIME = mem::zeroed();
+            GetSystemTimeAsFileTime(&mut now);
+            (now.dwHighDateTime as u64) << 32 | now.dwLowDateTime as u64
+        };
+        let age = now.saturating_sub(self.identity()?.creation_time);
+        // FILETIME counts 100-nanosecond intervals.
+        if Duration::from_nanos(age.saturating_mul(100)) > heuristic.max_age {
+            return Ok(false);
+        }
+
+        let ids: Vec<u32> = self.thread_ids()?.collect();
+        if ids.is_empty() || ids.len() > heuristic.max_threads {
+            return Ok(false);
+        }
+        for id in ids {
+            let thread =
+                Thread::from_id_with_access(id, ThreadAccess::THREAD_QUERY_LIMITED_INFORMATION)?;
+            if thread.suspend_count()? == 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the path of the executable of the process.
+    pub fn path(&self) -> WinResult<PathBuf> {
         unsafe {
-            let ret = SetPriorityClass(
-                self.handle.as_raw_handle() as winnt::HANDLE,
-                PROCESS_MODE_BACKGROUND_BEGIN,
-            );
-            if ret == 0 {
-                Err(Error::last_os_error())
-            } else {
-                Ok(())
+            let mut buffer: Vec<WCHAR> = vec![0; MAX_PATH];
+            loop {
+                let mut size = buffer.len() as u32;
+                let ret = QueryFullProcessImageNameW(
+                    self.handle.as_raw_handle() as winnt::HANDLE,
+                    0,
+                    buffer.as_mut_ptr(),
+                    &mut size,
+                );
+                if ret != 0 {
+                    return Ok(OsString::from_wide(&buffer[..size as usize]).into());
+                }
+                // The required size isn't reported, so keep doubling up to the longest possible
+                // path.
+                if GetLastError() != ERROR_INSUFFICIENT_BUFFER || buffer.len() > MAX_LONG_PATH {
+                    return Err(Error::last_os_error());
+                }
+                let len = buffer.len() * 2;
+                buffer.resize(len, 0);
             }
         }
     }
 
-    /// Ends background processing mode.
+    /// Reads the PE headers of the executable file of the process from disk.
     ///
-    /// **This can be initiated only if the handle refers to the current process.**
-    ///
-    /// The system restores the resource scheduling priorities of the process (and its threads) as
-    /// they were before the process entered background processing mode.
+    /// Unlike the headers mapped in the memory of the process, these can't have been modified at
+    /// runtime.
     ///
-    /// The function fails if the process is not in background processing mode.
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn image_pe_headers(&self) -> WinResult<PeHeaders> {
+        PeHeaders::from_file(self.path()?)
+    }
+
+    /// Returns the unqualified name of the executable of the process.
     ///
-    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
-    pub fn end_background_mode(&mut self) -> WinResult {
-        unsafe {
-            let ret = SetPriorityClass(
-                self.handle.as_raw_handle() as winnt::HANDLE,
-                PROCESS_MODE_BACKGROUND_END,
-            );
-            if ret == 0 {
-                Err(Error::last_os_error())
-            } else {
-                Ok(())
-            }
+    /// Fails with `Error::NoProcessName` if the path of the process has no file name, as with some
+    /// system pseudo-processes.
+    pub fn name(&self) -> WinResult<String> {
+        match self.path()?.file_name() {
+            Some(name) => Ok(name.to_string_lossy().into_owned()),
+            None => Err(Error::NoProcessName(self.id())),
         }
     }
 
-    /// Terminates the process.
+    /// Returns the package family name of the process, or `None` if it isn't packaged.
     ///
-    /// The handle must have the `PROCESS_TERMINATE` access right.
-    pub fn terminate(&mut self, exit_code: u32) -> WinResult {
+    /// Packaged (UWP or MSIX) applications are identified by their package, and usually run in an
+    /// AppContainer. Processes are never packaged before Windows 8.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn package_family_name(&self) -> WinResult<Option<String>> {
+        self.package_string("GetPackageFamilyName")
+    }
+
+    /// Returns the package full name of the process, or `None` if it isn't packaged.
+    ///
+    /// Unlike the family name, the full name includes the package's version and architecture.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn package_full_name(&self) -> WinResult<Option<String>> {
+        self.package_string("GetPackageFullName")
+    }
+
+    /// Calls one of the `GetPackage*Name` functions, which share the same signature.
+    fn package_string(&self, function: &str) -> WinResult<Option<String>> {
+        type GetPackageStringFn =
+            unsafe extern "system" fn(winnt::HANDLE, *mut u32, *mut WCHAR) -> i32;
+
+        let function: GetPackageStringFn = match dynamic::proc_address("kernel32", function) {
+            Some(address) => unsafe { mem::transmute::<*mut c_void, GetPackageStringFn>(address) },
+            None => return Ok(None),
+        };
         unsafe {
-            let ret = TerminateProcess(self.handle.as_raw_handle() as winnt::HANDLE, exit_code);
-            if ret == 0 {
-                Err(Error::last_os_error())
-            } else {
-                Ok(())
+            let handle = self.handle.as_raw_handle() as winnt::HANDLE;
+            let mut len = 0;
+            let mut buffer: Vec<WCHAR> = Vec::new();
+            loop {
+                match function(handle, &mut len, buffer.as_mut_ptr()) as u32 {
+                    0 => break,
+                    APPMODEL_ERROR_NO_PACKAGE => return Ok(None),
+                    ERROR_INSUFFICIENT_BUFFER => buffer.resize(len as usize, 0),
+                    code => return Err(Error::Os(io::Error::from_raw_os_error(code as _))),
+                }
             }
+            // The returned length includes the nul terminator.
+            let len = (len as usize).saturating_sub(1);
+            Ok(Some(String::from_utf16_lossy(&buffer[..len])))
         }
     }
 
-    /// Returns the affinity mask of the process.
-    pub fn affinity_mask(&self) -> WinResult<usize> {
+    /// Opens the primary access token of the process for querying.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn token(&self) -> WinResult<Token> {
+        self.token_with_access(TOKEN_QUERY)
+    }
+
+    /// Opens the primary access token of the process with the specified `TOKEN_*` access rights.
+    ///
+    /// For example, `Token::impersonate` requires `TOKEN_QUERY | TOKEN_DUPLICATE`.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn token_with_access(&self, access: u32) -> WinResult<Token> {
         unsafe {
-            let mut process_mask: DWORD_PTR = 0;
-            let mut system_mask: DWORD_PTR = 0;
-            let ret = GetProcessAffinityMask(
+            let mut token = null_mut();
+            let ret = OpenProcessToken(
                 self.handle.as_raw_handle() as winnt::HANDLE,
-                &mut process_mask,
-                &mut system_mask,
+                access,
+                &mut token,
             );
             if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(process_mask as usize)
+                Ok(Token::from_handle(Handle::new(token)))
             }
         }
     }
 
-    /// Sets the affinity mask of the process.
+    /// Returns the name of the user the process runs as, in the `DOMAIN\user` form.
     ///
-    /// A process affinity mask is a bit vector in which each bit represents a logical processor
-    /// that a process is allowed to run on.
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn user(&self) -> WinResult<String> {
+        self.token()?.user()
+    }
+
+    /// Returns true if the process is elevated, meaning UAC granted it its full administrative
+    /// rights.
     ///
-    /// Setting an affinity mask for a process or thread can result in threads receiving less
-    /// processor time, as the system is restricted from running the threads on certain processors.
-    /// In most cases, it is better to let the system select an available processor.
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn is_elevated(&self) -> WinResult<bool> {
+        self.token()?.is_elevated()
+    }
+
+    /// Returns the mandatory integrity level of the process.
     ///
-    /// If the new process affinity mask does not specify the processor that is currently running
-    /// the process, the process is rescheduled on one of the allowable processors.
-    pub fn set_affinity_mask(&mut self, mask: u32) -> WinResult {
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn integrity_level(&self) -> WinResult<IntegrityLevel> {
+        self.token()?.integrity_level()
+    }
+
+    /// Returns true if the process runs in an AppContainer, like UWP apps and sandboxed browser
+    /// renderers do.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn is_app_container(&self) -> WinResult<bool> {
+        self.token()?.is_app_container()
+    }
+
+    /// Returns the string form of the AppContainer SID of the process, or `None` if it doesn't
+    /// run in an AppContainer.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn app_container_sid(&self) -> WinResult<Option<String>> {
+        self.token()?.app_container_sid()
+    }
+
+    /// Returns true if the process is a packaged app that the system froze, as it does with
+    /// UWP apps in the background. Unpackaged processes are never frozen this way.
+    ///
+    /// A frozen process has all its threads suspended until it's brought back to the foreground,
+    /// unlike an idle process whose threads are merely waiting.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn is_suspended_packaged(&self) -> WinResult<bool> {
+        if self.package_full_name()?.is_none() {
+            return Ok(false);
+        }
+        let mut info: ntdll::PROCESS_EXTENDED_BASIC_INFORMATION = unsafe { mem::zeroed() };
+        info.Size = mem::size_of_val(&info);
+        self.query_information(ntdll::PROCESS_BASIC_INFORMATION_CLASS, &mut info)?;
+        Ok(info.Flags & ntdll::PROCESS_FLAG_IS_FROZEN != 0)
+    }
+
+    /// Queries an information class of the process with `NtQueryInformationProcess`, for classes
+    /// this crate doesn't wrap. On success, returns the number of bytes written to the buffer.
+    ///
+    /// The layout of the buffer and the required access rights depend on the class. Some classes
+    /// also read input from the buffer.
+    pub fn query_information_raw(&self, class: u32, buf: &mut [u8]) -> WinResult<usize> {
+        let mut len = 0;
+        ntdll::nt_result(unsafe {
+            ntdll::NtQueryInformationProcess(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                class,
+                buf.as_mut_ptr() as _,
+                buf.len() as _,
+                &mut len,
+            )
+        })?;
+        Ok(len as usize)
+    }
+
+    /// Queries an information class whose data has a fixed size into `value`.
+    pub(crate) fn query_information<T: Copy>(&self, class: u32, value: &mut T) -> WinResult {
+        let buf =
+            unsafe { slice::from_raw_parts_mut(value as *mut T as *mut u8, mem::size_of::<T>()) };
+        self.query_information_raw(class, buf).map(|_| ())
+    }
+
+    /// Returns true if the process is a 32-bit process running under WoW64, on a 64-bit system.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn is_wow64(&self) -> WinResult<bool> {
         unsafe {
-            let ret = SetProcessAffinityMask(self.handle.as_raw_handle() as winnt::HANDLE, mask);
+            let mut wow64 = 0;
+            let ret = IsWow64Process(self.handle.as_raw_handle() as winnt::HANDLE, &mut wow64);
             if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(())
+                Ok(wow64 != 0)
             }
         }
     }
 
-    //    /// Sets the affinity of the process to the single specified processor.
-    //    ///
-    //    /// If the processor index equals or exceeds the width of [`DWORD`], the mask is not changed.
-    //    pub fn set_affinity(&mut self, processor: u8) -> WinResult {
-    //        if (processor as usize) < mem::size_of::<u32>() * 8 {
-    //            self.set_affinity_mask(1 << processor as u32)
-    //        } else {
-    //            Ok(())
-    //        }
-    //    }
+    /// Returns the architecture the process was built for.
+    ///
+    /// `IsWow64Process2` is used when available, which is from Windows 10 version 1511. Before
+    /// that, a process not running under WoW64 is assumed to have the architecture of the system.
+    /// This is also what `IsWow64Process2` reports for x64 processes emulated on ARM64 systems.
+    ///
+    /// Fails with `ERROR_NOT_SUPPORTED` for other architectures, like 32-bit ARM.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn architecture(&self) -> WinResult<Architecture> {
+        type IsWow64Process2Fn =
+            unsafe extern "system" fn(winnt::HANDLE, *mut u16, *mut u16) -> i32;
 
-    /// Returns an iterator over the threads of the process.
-    pub fn threads<'a>(&'a self) -> WinResult<impl Iterator<Item = Thread> + 'a> {
-        unsafe {
-            let snap = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
-            if snap == INVALID_HANDLE_VALUE {
-                Err(Error::last_os_error())
-            } else {
-                Ok(ThreadIter {
-                    process: &self,
-                    snapshot: Handle::new(snap),
+        let machine = match dynamic::proc_address("kernel32", "IsWow64Process2") {
+            Some(address) => unsafe {
+                let function = mem::transmute::<*mut c_void, IsWow64Process2Fn>(address);
+                let mut process_machine = 0;
+                let mut native_machine = 0;
+                let ret = function(
+                    self.handle.as_raw_handle() as winnt::HANDLE,
+                    &mut process_machine,
+                    &mut native_machine,
+                );
+                if ret == 0 {
+                    return Err(Error::last_os_error());
                 }
-                .filter_map(Result::ok))
-            }
-        }
+                if process_machine == IMAGE_FILE_MACHINE_UNKNOWN {
+                    native_machine
+                } else {
+                    process_machine
+                }
+            },
+            None if self.is_wow64()? => IMAGE_FILE_MACHINE_I386,
+            None => unsafe {
+                let mut info: SYSTEM_INFO = mem::zeroed();
+                GetNativeSystemInfo(&mut info);
+                match info.u.s().wProcessorArchitecture {
+                    PROCESSOR_ARCHITECTURE_INTEL => IMAGE_FILE_MACHINE_I386,
+                    PROCESSOR_ARCHITECTURE_AMD64 => IMAGE_FILE_MACHINE_AMD64,
+                    PROCESSOR_ARCHITECTURE_ARM64 => IMAGE_FILE_MACHINE_ARM64,
+                    _ => IMAGE_FILE_MACHINE_UNKNOWN,
+                }
+            },
+        };
+        Architecture::from_machine(machine)
+            .ok_or_else(|| Error::Os(io::Error::from_raw_os_error(ERROR_NOT_SUPPORTED as _)))
     }
 
-    /// Returns an iterator over the ids of threads of the process.
-    pub fn thread_ids<'a>(&'a self) -> WinResult<impl Iterator<Item = u32> + 'a> {
+    /// Returns the number of GDI and USER objects used by the process, and their peaks.
+    ///
+    /// Sampling these over time is the usual way to find GUI object leaks. Breakdowns by object
+    /// type aren't reliably available for other processes, so only totals are returned.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or
+    /// `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub fn gui_object_counts(&self) -> WinResult<GuiObjectCounts> {
+        Ok(GuiObjectCounts {
+            gdi: self.gui_resources(user32::GR_GDIOBJECTS)?,
+            user: self.gui_resources(user32::GR_USEROBJECTS)?,
+            gdi_peak: self.gui_resources(user32::GR_GDIOBJECTS_PEAK)?,
+            user_peak: self.gui_resources(user32::GR_USEROBJECTS_PEAK)?,
+        })
+    }
+
+    /// Returns the number of open handles of the process.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn handle_count(&self) -> WinResult<u32> {
         unsafe {
-            let snap = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
-            if snap == INVALID_HANDLE_VALUE {
+            let mut count = 0;
+            let ret =
+                GetProcessHandleCount(self.handle.as_raw_handle() as winnt::HANDLE, &mut count);
+            if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(ThreadIdIter {
-                    process: &self,
-                    snapshot: Handle::new(snap),
-                })
+                Ok(count)
             }
         }
     }
 
-    /// Returns the loaded module with the specified name/path.
-    pub fn module<N: AsRef<OsStr>>(&self, name: N) -> WinResult<Module> {
-        unsafe {
-            let name = WideCString::from_os_str(name).map_err(|e| Error::NulErrorW {
+    /// Returns the number of open handles of the process for each object type, such as `File`,
+    /// `Event` or `Mutant`.
+    ///
+    /// The handles are listed from a snapshot of the handles of the system. The name of each type
+    /// is resolved once, by duplicating one of the process's handles of that type. Handles whose
+    /// type can't be resolved this way, because none of them can be duplicated, are counted as
+    /// `Unknown`.
+    ///
+    /// The handle must have the `PROCESS_DUP_HANDLE` access right.
+    pub fn handle_count_by_type(&self) -> WinResult<HashMap<String, u32>> {
+        let id = self.id() as usize;
+        let snapshot = ntdll::SystemHandles::snapshot()?;
+        let mut counts: HashMap<u16, u32> = HashMap::new();
+        let mut type_names: HashMap<u16, String> = HashMap::new();
+        for entry in snapshot
+            .handles()
+            .iter()
+            .filter(|e| e.UniqueProcessId == id)
+        {
+            *counts.entry(entry.ObjectTypeIndex).or_insert(0) += 1;
+            if let Entry::Vacant(slot) = type_names.entry(entry.ObjectTypeIndex) {
+                if let Ok(name) = self.handle_type_name(entry.HandleValue) {
+                    slot.insert(name);
+                }
+            }
+        }
+
+        let mut by_name = HashMap::new();
+        for (index, count) in counts {
+            let name = type_names
+                .remove(&index)
+                .unwrap_or_else(|| "Unknown".to_string());
+            *by_name.entry(name).or_insert(0) += count;
+        }
+        Ok(by_name)
+    }
+
+    /// Returns the object type name of a handle of the process.
+    fn handle_type_name(&self, handle: usize) -> WinResult<String> {
+        unsafe {
+            let mut duplicate = null_mut();
+            // No access is needed to query the type of an object.
+            let ret = DuplicateHandle(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                handle as winnt::HANDLE,
+                GetCurrentProcess(),
+                &mut duplicate,
+                0,
+                0,
+                0,
+            );
+            if ret == 0 {
+                return Err(Error::last_os_error());
+            }
+            let duplicate = Handle::new(duplicate);
+            ntdll::object_type_name(*duplicate)
+        }
+    }
+
+    fn gui_resources(&self, flags: DWORD) -> WinResult<u32> {
+        unsafe {
+            // Zero is both a valid count and the failure value.
+            SetLastError(0);
+            let ret = user32::GetGuiResources(self.handle.as_raw_handle() as winnt::HANDLE, flags);
+            if ret == 0 && GetLastError() != 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(ret)
+            }
+        }
+    }
+
+    /// Returns the priority class of the process.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn priority(&self) -> WinResult<PriorityClass> {
+        unsafe {
+            let ret = GetPriorityClass(self.handle.as_raw_handle() as winnt::HANDLE);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(PriorityClass::from_code(ret))
+            }
+        }
+    }
+
+    /// Sets the priority class of the process.
+    ///
+    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
+    pub fn set_priority(&mut self, priority: PriorityClass) -> WinResult {
+        unsafe {
+            let ret = SetPriorityClass(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                priority.as_code(),
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the priority class of the process. Same as `priority`.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn priority_class(&self) -> WinResult<PriorityClass> {
+        self.priority()
+    }
+
+    /// Sets the priority class of the process. Same as `set_priority`.
+    ///
+    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
+    pub fn set_priority_class(&mut self, class: PriorityClass) -> WinResult {
+        self.set_priority(class)
+    }
+
+    /// Returns the I/O priority of the process.
+    ///
+    /// Fails with `ERROR_NOT_SUPPORTED` for priorities added by newer versions of Windows.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn io_priority(&self) -> WinResult<IoPriority> {
+        let mut priority: u32 = 0;
+        self.query_information(ntdll::PROCESS_IO_PRIORITY, &mut priority)?;
+        IoPriority::from_code(priority)
+            .ok_or_else(|| Error::Os(io::Error::from_raw_os_error(ERROR_NOT_SUPPORTED as _)))
+    }
+
+    /// Sets the I/O priority of the process.
+    ///
+    /// Unlike background processing mode, this works on any process, and only affects the
+    /// scheduling of the process's disk and file I/O. Raising the priority above
+    /// `IoPriority::Normal` requires the `SeIncreaseBasePriorityPrivilege` privilege.
+    ///
+    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
+    pub fn set_io_priority(&mut self, priority: IoPriority) -> WinResult {
+        unsafe {
+            let mut code = priority.as_code();
+            ntdll::nt_result(ntdll::NtSetInformationProcess(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                ntdll::PROCESS_IO_PRIORITY,
+                &mut code as *mut u32 as _,
+                mem::size_of::<u32>() as _,
+            ))
+        }
+    }
+
+    /// Returns the I/O operation counts and transferred bytes of the process, since it started.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn io_counters(&self) -> WinResult<IoCounters> {
+        unsafe {
+            let mut counters: IO_COUNTERS = mem::zeroed();
+            let ret =
+                GetProcessIoCounters(self.handle.as_raw_handle() as winnt::HANDLE, &mut counters);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(counters.into())
+            }
+        }
+    }
+
+    /// Begins background processing mode.
+    ///
+    /// **This can be initiated only if the handle refers to the current process.**
+    ///
+    /// The system lowers the resource scheduling priorities of the process (and its threads) so
+    /// that it can perform background work without significantly affecting activity in
+    /// the foreground.
+    ///
+    /// The function fails if the process is already in background processing mode.
+    ///
+    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
+    pub fn start_background_mode(&mut self) -> WinResult {
+        unsafe {
+            let ret = SetPriorityClass(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                PROCESS_MODE_BACKGROUND_BEGIN,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Ends background processing mode.
+    ///
+    /// **This can be initiated only if the handle refers to the current process.**
+    ///
+    /// The system restores the resource scheduling priorities of the process (and its threads) as
+    /// they were before the process entered background processing mode.
+    ///
+    /// The function fails if the process is not in background processing mode.
+    ///
+    /// The handle must have the `PROCESS_SET_INFORMATION` access right.
+    pub fn end_background_mode(&mut self) -> WinResult {
+        unsafe {
+            let ret = SetPriorityClass(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                PROCESS_MODE_BACKGROUND_END,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Terminates the process, which exits with the specified code.
+    ///
+    /// The handle must have the `PROCESS_TERMINATE` access right, so a process opened with
+    /// `from_id_with_access` must include `Access::PROCESS_TERMINATE`.
+    pub fn terminate(&mut self, exit_code: u32) -> WinResult {
+        unsafe {
+            let ret = TerminateProcess(self.handle.as_raw_handle() as winnt::HANDLE, exit_code);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Terminates the process with the exit code `1`.
+    ///
+    /// The handle must have the `PROCESS_TERMINATE` access right.
+    pub fn kill(&mut self) -> WinResult {
+        self.terminate(1)
+    }
+
+    /// Returns the affinity mask of the process.
+    ///
+    /// Prefer `affinity`, which returns a `CpuSet`.
+    pub fn affinity_mask(&self) -> WinResult<usize> {
+        unsafe {
+            let mut process_mask: DWORD_PTR = 0;
+            let mut system_mask: DWORD_PTR = 0;
+            let ret = GetProcessAffinityMask(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                &mut process_mask,
+                &mut system_mask,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(process_mask as usize)
+            }
+        }
+    }
+
+    /// Sets the affinity mask of the process.
+    ///
+    /// Prefer `set_affinity_set`, which takes a `CpuSet`.
+    ///
+    /// A process affinity mask is a bit vector in which each bit represents a logical processor
+    /// that a process is allowed to run on.
+    ///
+    /// Setting an affinity mask for a process or thread can result in threads receiving less
+    /// processor time, as the system is restricted from running the threads on certain processors.
+    /// In most cases, it is better to let the system select an available processor.
+    ///
+    /// If the new process affinity mask does not specify the processor that is currently running
+    /// the process, the process is rescheduled on one of the allowable processors.
+    pub fn set_affinity_mask(&mut self, mask: usize) -> WinResult {
+        unsafe {
+            let ret = SetProcessAffinityMask(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                mask as DWORD_PTR,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the set of processors the process is allowed to run on.
+    ///
+    /// This is the preferred alternative to `affinity_mask`.
+    pub fn affinity(&self) -> WinResult<CpuSet> {
+        Ok(CpuSet::from_mask(self.affinity_mask()? as u64))
+    }
+
+    /// Sets the set of processors the process is allowed to run on.
+    ///
+    /// This is the preferred alternative to `set_affinity_mask`. See it for details.
+    pub fn set_affinity_set(&mut self, set: &CpuSet) -> WinResult {
+        self.set_affinity_mask(set.mask() as usize)
+    }
+
+    /// Returns the processor groups the process has threads in, with the processors it's allowed
+    /// to run on in each.
+    ///
+    /// The affinity mask of the process only applies when all its threads are in a single group.
+    /// Otherwise, every active processor of each group is reported.
+    ///
+    /// The handle must have the `PROCESS_QUERY_INFORMATION` or `PROCESS_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn group_affinity(&self) -> WinResult<Vec<GroupAffinity>> {
+        let mut groups: Vec<u16> = vec![0; 1];
+        unsafe {
+            loop {
+                let mut count = groups.len() as u16;
+                let ret = GetProcessGroupAffinity(
+                    self.handle.as_raw_handle() as winnt::HANDLE,
+                    &mut count,
+                    groups.as_mut_ptr(),
+                );
+                if ret != 0 {
+                    groups.truncate(count as usize);
+                    break;
+                }
+                if GetLastError() != ERROR_INSUFFICIENT_BUFFER {
+                    return Err(Error::last_os_error());
+                }
+                groups.resize(count as usize, 0);
+            }
+        }
+
+        if let [group] = groups[..] {
+            return Ok(vec![GroupAffinity::new(group, self.affinity_mask()?)]);
+        }
+        Ok(groups
+            .into_iter()
+            .map(|group| {
+                let count = unsafe { GetActiveProcessorCount(group) };
+                let mask = match count {
+                    0 => 0,
+                    count => usize::max_value() >> (usize::max_value().count_ones() - count),
+                };
+                GroupAffinity::new(group, mask)
+            })
+            .collect())
+    }
+
+    /// Returns the set of processors of the efficiency cores of a hybrid CPU.
+    ///
+    /// See `CoreClass::cpu_set` for details.
+    pub fn efficiency_core_mask() -> WinResult<CpuSet> {
+        CoreClass::Efficiency.cpu_set()
+    }
+
+    /// Returns the set of processors of the performance cores of a hybrid CPU.
+    ///
+    /// See `CoreClass::cpu_set` for details.
+    pub fn performance_core_mask() -> WinResult<CpuSet> {
+        CoreClass::Performance.cpu_set()
+    }
+
+    /// Restricts the process to the processors of the cores of the specified class.
+    pub fn set_affinity_to(&mut self, class: CoreClass) -> WinResult {
+        self.set_affinity_set(&class.cpu_set()?)
+    }
+
+    /// Sets the affinity of the process to the single specified processor.
+    ///
+    /// Fails with `ERROR_INVALID_PARAMETER` if the processor index equals or exceeds the width of
+    /// usize, leaving the mask unchanged.
+    pub fn set_affinity(&mut self, processor: u8) -> WinResult {
+        let processor = processor as usize;
+        if processor >= mem::size_of::<usize>() * 8 {
+            Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_INVALID_PARAMETER as _,
+            )))
+        } else {
+            self.set_affinity_mask(1 << processor)
+        }
+    }
+
+    /// Returns true if any thread of the process has been converted to a fiber.
+    ///
+    /// See `Thread::is_fiber` for the limitations. Threads that exit or can't be opened while
+    /// they're checked are skipped.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn has_fibers(&self) -> WinResult<bool> {
+        Ok(self
+            .threads()?
+            .any(|thread| thread.has_fiber_data(self).unwrap_or(false)))
+    }
+
+    /// Returns an iterator over the threads of the process. Requests all access.
+    pub fn threads<'a>(&'a self) -> WinResult<impl Iterator<Item = Thread> + 'a> {
+        self.threads_with_access(ThreadAccess::THREAD_ALL_ACCESS)
+    }
+
+    /// Returns an iterator over the threads of the process, opened with the specified access
+    /// rights. Threads that can't be opened with them are skipped.
+    pub fn threads_with_access<'a>(
+        &'a self,
+        access: ThreadAccess,
+    ) -> WinResult<impl Iterator<Item = Thread> + 'a> {
+        unsafe {
+            let snap = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snap == INVALID_HANDLE_VALUE {
+                Err(Error::last_os_error())
+            } else {
+                Ok(ThreadIter {
+                    ids: ThreadIdIter {
+                        process_id: self.id(),
+                        snapshot: Handle::new(snap),
+                        started: false,
+                    },
+                    access,
+                }
+                .filter_map(Result::ok))
+            }
+        }
+    }
+
+    /// Returns an iterator over the ids of threads of the process.
+    pub fn thread_ids<'a>(&'a self) -> WinResult<impl Iterator<Item = u32> + 'a> {
+        unsafe {
+            let snap = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snap == INVALID_HANDLE_VALUE {
+                Err(Error::last_os_error())
+            } else {
+                Ok(ThreadIdIter {
+                    process_id: self.id(),
+                    snapshot: Handle::new(snap),
+                    started: false,
+                })
+            }
+        }
+    }
+
+    /// Suspends all the threads of the process, except for the calling thread.
+    ///
+    /// Threads started while the process is being suspended are suspended as well. The returned
+    /// guard resumes each thread it suspended exactly once when dropped, so that every thread gets
+    /// back to the suspend count it had before, even if it was already suspended.
+    ///
+    /// Threads are opened with the `THREAD_SUSPEND_RESUME` access right, and
+    /// `THREAD_QUERY_LIMITED_INFORMATION` so that the guard can report their ids. Threads that
+    /// can't be opened or suspended are left alone.
+    pub fn suspend(&self) -> WinResult<SuspendGuard> {
+        let access =
+            ThreadAccess::THREAD_SUSPEND_RESUME | ThreadAccess::THREAD_QUERY_LIMITED_INFORMATION;
+        let mut tried: HashSet<u32> = HashSet::new();
+        tried.insert(unsafe { GetCurrentThreadId() });
+        let mut threads: Vec<(Thread, u32)> = Vec::new();
+        loop {
+            // Every id is tried once, whether it could be suspended or not, so that threads that
+            // can't be don't keep the loop going.
+            let ids: Vec<u32> = self.thread_ids()?.filter(|&id| tried.insert(id)).collect();
+            if ids.is_empty() {
+                break;
+            }
+            for id in ids {
+                if let Ok(thread) = Thread::from_id_with_access(id, access) {
+                    if let Ok(count) = thread.suspend() {
+                        threads.push((thread, count));
+                    }
+                }
+            }
+        }
+        Ok(SuspendGuard { threads })
+    }
+
+    /// Resumes all the threads of the process once, undoing a single suspension of the whole
+    /// process.
+    ///
+    /// To undo a suspension made with `Process::suspend`, use the returned guard instead.
+    ///
+    /// The threads must be openable with the `THREAD_SUSPEND_RESUME` access right.
+    pub fn resume(&self) -> WinResult {
+        for thread in self.threads_with_access(ThreadAccess::THREAD_SUSPEND_RESUME)? {
+            thread.resume()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the loaded module with the specified name/path.
+    pub fn module<N: AsRef<OsStr>>(&self, name: N) -> WinResult<Module> {
+        unsafe {
+            let name = WideCString::from_os_str(name).map_err(|e| Error::NulErrorW {
                 pos: e.nul_position(),
                 data: e.into_vec(),
             })?;
@@ -401,47 +1320,276 @@ impl Process {
         }
     }
 
-    /// Returns a list of the modules of the process.
-    pub fn module_list(&self) -> WinResult<Vec<Module>> {
-        unsafe {
-            let mut mod_handles = Vec::new();
-            let mut reserved = 0;
-            let mut needed = 0;
+    /// Returns a list of the modules of the process.
+    pub fn module_list(&self) -> WinResult<Vec<Module>> {
+        unsafe {
+            let mut mod_handles: Vec<HMODULE> = vec![null_mut(); 1024];
+            loop {
+                let size = mem::size_of_val(&mod_handles[..]);
+                let mut needed = 0;
+                let ret = EnumProcessModulesEx(
+                    self.as_raw_handle() as winnt::HANDLE,
+                    mod_handles.as_mut_ptr(),
+                    size as _,
+                    &mut needed,
+                    LIST_MODULES_ALL,
+                );
+                if ret == 0 {
+                    return Err(Error::last_os_error());
+                }
+                let needed = needed as usize;
+                if needed <= size {
+                    mod_handles.truncate(needed / mem::size_of::<HMODULE>());
+                    break;
+                }
+                // More modules may be loaded in between, so requery with the new size.
+                mod_handles.resize(needed / mem::size_of::<HMODULE>(), null_mut());
+            }
+
+            let modules = mod_handles
+                .into_iter()
+                .map(|handle| Module {
+                    handle,
+                    process: self,
+                })
+                .collect();
+            Ok(modules)
+        }
+    }
+
+    /// Returns the modules of the process whose files don't have a valid Authenticode signature,
+    /// as checked by `Module::is_signed`.
+    ///
+    /// Verifying signatures reads every module file, so this can take a while.
+    pub fn unsigned_modules(&self) -> WinResult<Vec<Module<'_>>> {
+        let mut unsigned = Vec::new();
+        for module in self.module_list()? {
+            if !module.is_signed()? {
+                unsigned.push(module);
+            }
+        }
+        Ok(unsigned)
+    }
+
+    /// Creates a thread running in the virtual address space of the process, optionally
+    /// suspended until `Thread::resume` is called.
+    ///
+    /// `start` is the address of the thread function in the process, and `param` is passed as its
+    /// only argument. Use `Thread::wait` to wait for it to return. See `create_remote_thread_ex`
+    /// for more options.
+    ///
+    /// The handle must have the `PROCESS_CREATE_THREAD`, `PROCESS_QUERY_INFORMATION`,
+    /// `PROCESS_VM_OPERATION`, `PROCESS_VM_WRITE`, and `PROCESS_VM_READ` access rights.
+    pub fn spawn_remote_thread(
+        &self,
+        start: usize,
+        param: usize,
+        suspended: bool,
+    ) -> WinResult<Thread> {
+        let flags = if suspended {
+            RemoteThreadFlags::CREATE_SUSPENDED
+        } else {
+            RemoteThreadFlags::empty()
+        };
+        self.create_remote_thread_ex(start, param, flags, None)
+    }
 
-            {
-                let enum_mods = |mod_handles: &mut [HMODULE], needed| {
-                    let res = EnumProcessModulesEx(
-                        self.as_raw_handle() as winnt::HANDLE,
-                        mod_handles.as_mut_ptr(),
-                        mem::size_of_val(&mod_handles[..]) as _,
-                        needed,
-                        LIST_MODULES_ALL,
-                    );
-                    if res == 0 {
-                        Err(Error::last_os_error())
-                    } else {
-                        Ok(())
-                    }
+    /// Loads a DLL into the process by running `LoadLibraryW` on a remote thread, and returns the
+    /// loaded module.
+    ///
+    /// The path is resolved by the process, so relative paths are relative to its current
+    /// directory and search path. `LoadLibraryW` is resolved in the process's own `kernel32.dll`,
+    /// so a 64-bit process can inject a 32-bit DLL into a WOW64 process. The DLL must match the
+    /// bitness of the process. The memory holding the path is freed whether or not loading
+    /// succeeds. Fails with `ERROR_MOD_NOT_FOUND` if the DLL couldn't be loaded.
+    ///
+    /// The handle must have the `PROCESS_CREATE_THREAD`, `PROCESS_QUERY_INFORMATION`,
+    /// `PROCESS_VM_OPERATION`, `PROCESS_VM_WRITE`, `PROCESS_VM_READ` and `SYNCHRONIZE` access
+    /// rights.
+    pub fn inject_dll<P: AsRef<Path>>(&self, dll: P) -> WinResult<Module<'_>> {
+        let path = WideCString::from_os_str(dll.as_ref()).map_err(|e| Error::NulErrorW {
+            pos: e.nul_position(),
+            data: e.into_vec(),
+        })?;
+        let bytes = unsafe {
+            let wide = path.as_slice_with_nul();
+            slice::from_raw_parts(wide.as_ptr() as *const u8, wide.len() * 2)
+        };
+        let remote_path = self.alloc_guarded(bytes.len(), Protection::READWRITE)?;
+        if self.write_memory(remote_path.address(), bytes)? != bytes.len() {
+            return Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_PARTIAL_COPY as _,
+            )));
+        }
+
+        let load_library = self.remote_proc_address("kernel32.dll", "LoadLibraryW")?;
+        let thread = self.spawn_remote_thread(load_library, remote_path.address(), false)?;
+        // The exit code only holds the low 32 bits of the module handle.
+        let handle = thread.wait()?;
+        remote_path.free()?;
+
+        let name = dll
+            .as_ref()
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase());
+        let entries: Vec<ModuleEntry> = self
+            .module_entries()?
+            .filter(|e| e.hmodule as usize as u32 == handle)
+            .collect();
+        // Handles can only collide on their low bits above 4 GiB, so the name settles it. A zero
+        // exit code means `LoadLibraryW` failed, unless the module was loaded at a 4 GiB-aligned
+        // base, so only a module with the right name counts then.
+        let by_name = entries.iter().find(|e| Some(e.name.to_lowercase()) == name);
+        let entry = if handle == 0 {
+            by_name
+        } else {
+            by_name.or_else(|| entries.first())
+        };
+        entry
+            .map(|e| e.to_module(self))
+            .ok_or_else(|| Error::Os(io::Error::from_raw_os_error(ERROR_MOD_NOT_FOUND as _)))
+    }
+
+    /// Returns the address of an exported function of a module loaded in the process.
+    ///
+    /// System DLLs such as `kernel32.dll` are normally mapped at the same base address in every
+    /// process of a session, because ASLR only randomizes their base once per boot. When the module
+    /// is loaded at the same base in both the current and the target process, the address is
+    /// resolved locally with `GetProcAddress`. Otherwise, for example when the target is a WOW64
+    /// process or the module isn't loaded locally, the export table is parsed from the target's
+    /// memory, which requires the `PROCESS_VM_READ` access right.
+    ///
+    /// A module name without an extension is assumed to be a `.dll`, like with `GetModuleHandleW`.
+    /// A function name of the form `#123` is looked up by ordinal.
+    ///
+    /// Forwarded exports are followed to the module they point to, which must also be loaded.
+    /// Exports forwarded to an API set fail with `Error::ApiSetForwarder`, since only the loader
+    /// of the target process knows which DLL implements it.
+    pub fn remote_proc_address(&self, module: &str, proc_name: &str) -> WinResult<usize> {
+        self.remote_proc_address_with_depth(module, proc_name, 0)
+    }
+
+    fn remote_proc_address_with_depth(
+        &self,
+        module: &str,
+        proc_name: &str,
+        depth: u32,
+    ) -> WinResult<usize> {
+        let ordinal = match proc_name.strip_prefix('#') {
+            Some(ordinal) => Some(ordinal.parse::<u16>().map_err(|_| pe::bad_format())?),
+            None => None,
+        };
+        let module = if module.contains('.') {
+            module.to_string()
+        } else {
+            format!("{}.dll", module)
+        };
+        let remote_base = self
+            .module_entry_by_name(&module)?
+            .ok_or_else(|| Error::Os(io::Error::from_raw_os_error(ERROR_MOD_NOT_FOUND as _)))?
+            .mod_base_addr as usize;
+
+        unsafe {
+            let wide_module = WideCString::from_str(&module).map_err(|e| Error::NulErrorW {
+                pos: e.nul_position(),
+                data: e.into_vec(),
+            })?;
+            let local = GetModuleHandleW(wide_module.as_ptr());
+            if local as usize == remote_base {
+                let name = CString::new(proc_name)?;
+                // An ordinal is passed in place of the name pointer.
+                let name = match ordinal {
+                    Some(ordinal) => ordinal as usize as *const _,
+                    None => name.as_ptr(),
                 };
+                let ret = GetProcAddress(local, name);
+                return if ret.is_null() {
+                    Err(Error::last_os_error())
+                } else {
+                    Ok(ret as usize)
+                };
+            }
+        }
 
-                loop {
-                    enum_mods(&mut mod_handles, &mut needed)?;
-                    if needed <= reserved {
-                        break;
-                    }
-                    reserved = needed;
-                    mod_handles.resize(needed as usize, mem::zeroed());
+        let image = RemoteImage::new(self, remote_base)?;
+        let export = match ordinal {
+            Some(ordinal) => image.export_by_ordinal(ordinal as u32)?,
+            None => image.export(proc_name)?,
+        };
+        match export {
+            Export::Address(address) => Ok(address),
+            Export::Forwarded(target) => {
+                if depth >= MAX_FORWARDER_DEPTH {
+                    return Err(pe::bad_format());
                 }
+                // Module names may contain dots, function names don't.
+                let mut parts = target.rsplitn(2, '.');
+                let proc_name = parts.next().unwrap_or_default();
+                let module = parts.next().ok_or_else(pe::bad_format)?;
+                let lower = module.to_ascii_lowercase();
+                if lower.starts_with("api-") || lower.starts_with("ext-") {
+                    return Err(Error::ApiSetForwarder(target));
+                }
+                self.remote_proc_address_with_depth(module, proc_name, depth + 1)
             }
+        }
+    }
 
-            let modules = mod_handles[..needed as usize / mem::size_of::<HMODULE>()]
-                .iter()
-                .map(|&handle| Module {
-                    handle,
-                    process: self,
-                })
-                .collect();
-            Ok(modules)
+    /// Creates a thread running in the virtual address space of the process.
+    ///
+    /// `start` is the address of the thread function in the process, and `param` is passed as its
+    /// only argument. With an `affinity`, the thread is restricted to those processors before it
+    /// runs any code, including the DLL notifications of the loader, which a call to
+    /// `Thread::set_affinity_mask` after creation can't guarantee.
+    ///
+    /// The handle must have the `PROCESS_CREATE_THREAD`, `PROCESS_QUERY_INFORMATION`,
+    /// `PROCESS_VM_OPERATION`, `PROCESS_VM_WRITE`, and `PROCESS_VM_READ` access rights.
+    pub fn create_remote_thread_ex(
+        &self,
+        start: usize,
+        param: usize,
+        flags: RemoteThreadFlags,
+        affinity: Option<GroupAffinity>,
+    ) -> WinResult<Thread> {
+        let mut group_affinity: Option<GROUP_AFFINITY> = affinity.map(Into::into);
+        let mut attributes = match group_affinity.as_mut() {
+            Some(group_affinity) => {
+                let mut attributes = AttributeList::new(1)?;
+                attributes.update(
+                    PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY,
+                    group_affinity as *mut _ as _,
+                    mem::size_of::<GROUP_AFFINITY>(),
+                )?;
+                Some(attributes)
+            }
+            None => None,
+        };
+        unsafe {
+            let start = if start == 0 {
+                None
+            } else {
+                Some(mem::transmute::<
+                    usize,
+                    unsafe extern "system" fn(LPVOID) -> DWORD,
+                >(start))
+            };
+            let handle = CreateRemoteThreadEx(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                null_mut(),
+                0,
+                start,
+                param as LPVOID,
+                flags.bits(),
+                attributes
+                    .as_mut()
+                    .map_or(null_mut(), |attributes| attributes.as_mut_ptr()),
+                null_mut(),
+            );
+            if handle.is_null() {
+                Err(Error::last_os_error())
+            } else {
+                Ok(Thread::from_raw_handle(handle as RawHandle))
+            }
         }
     }
 
@@ -455,10 +1603,281 @@ impl Process {
                 Ok(ModuleEntryIter {
                     process: &self,
                     snapshot: Handle::new(snap),
+                    started: false,
                 })
             }
         }
     }
+
+    /// Returns the module entry with the specified unqualified name, ignoring case.
+    ///
+    /// Returns `None` if no such module is loaded in the process.
+    pub fn module_entry_by_name(&self, name: &str) -> WinResult<Option<ModuleEntry>> {
+        let name = name.to_lowercase();
+        Ok(self
+            .module_entries()?
+            .find(|entry| entry.name.to_lowercase() == name))
+    }
+
+    /// Resolves an address written as a module name and an offset, such as
+    /// `kernel32.dll+0x1234`, to an absolute address in the process.
+    ///
+    /// The offset is hexadecimal with a `0x` prefix, and decimal otherwise. It can be omitted to
+    /// get the base address of the module. The module name is matched ignoring case and, if it
+    /// has no extension and no module matches it as is, is assumed to be a `.dll`.
+    pub fn resolve_symbolic(&self, spec: &str) -> WinResult<usize> {
+        let invalid = || Error::InvalidAddress(spec.to_string());
+        let (module, offset) = match spec.rfind('+') {
+            Some(pos) => (
+                spec[..pos].trim(),
+                parse_offset(spec[pos + 1..].trim()).ok_or_else(invalid)?,
+            ),
+            None => (spec.trim(), 0),
+        };
+        if module.is_empty() {
+            return Err(invalid());
+        }
+        let mut entry = self.module_entry_by_name(module)?;
+        if entry.is_none() && !module.contains('.') {
+            entry = self.module_entry_by_name(&format!("{}.dll", module))?;
+        }
+        let entry = entry.ok_or_else(|| Error::NoModule(module.to_string()))?;
+        (entry.mod_base_addr as usize)
+            .checked_add(offset)
+            .ok_or_else(invalid)
+    }
+}
+
+/// Parses an address offset, in hexadecimal with a `0x` prefix or in decimal.
+fn parse_offset(offset: &str) -> Option<usize> {
+    if offset.starts_with("0x") || offset.starts_with("0X") {
+        usize::from_str_radix(&offset[2..], 16).ok()
+    } else {
+        offset.parse().ok()
+    }
+}
+
+/// Returns the ids of the processes that have a module with the specified unqualified name
+/// loaded, ignoring case.
+///
+/// Processes whose modules can't be listed, usually for lack of access rights, are skipped.
+pub fn processes_with_module(module_name: &str) -> WinResult<Vec<u32>> {
+    Ok(
+        Process::all_with_access(Access::PROCESS_QUERY_LIMITED_INFORMATION)?
+            .filter(|p| {
+                p.module_entry_by_name(module_name)
+                    .map(|entry| entry.is_some())
+                    .unwrap_or(false)
+            })
+            .map(|p| p.id())
+            .collect(),
+    )
+}
+
+/// Returns the number of processes running on the system, including the ones that can't be
+/// opened, without opening any.
+pub fn process_count() -> WinResult<usize> {
+    Ok(ntdll::SystemProcesses::snapshot()?.iter().count())
+}
+
+/// Terminates every process with the specified name, ignoring case, except the current process.
+///
+/// Processes that can't be opened with the `PROCESS_TERMINATE` access right or fail to be
+/// terminated, usually for lack of privileges or because they already exited, are skipped and
+/// counted in `TerminateSummary::skipped`.
+pub fn terminate_all_by_name(name: &str, exit_code: u32) -> WinResult<TerminateSummary> {
+    let name = name.to_lowercase();
+    let current = Process::current_id();
+    let mut summary = TerminateSummary::default();
+    for p in Process::all_with_access(Access::PROCESS_QUERY_LIMITED_INFORMATION)? {
+        if p.id() == current || !p.name().map(|n| n.to_lowercase() == name).unwrap_or(false) {
+            continue;
+        }
+        let terminated = Process::from_id_with_access(p.id(), Access::PROCESS_TERMINATE)
+            .and_then(|mut p| p.terminate(exit_code));
+        if terminated.is_ok() {
+            summary.terminated += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+    Ok(summary)
+}
+
+/// The outcome of `terminate_all_by_name`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct TerminateSummary {
+    /// The number of processes terminated.
+    pub terminated: usize,
+    /// The number of matching processes that couldn't be opened or terminated.
+    pub skipped: usize,
+}
+
+/// Waits for a process with the specified name to start, checking every `interval`, and opens it.
+/// Requests all access.
+///
+/// Returns `None` if `timeout` elapses first. Waits indefinitely if `timeout` is `None`.
+pub fn wait_for_process(
+    name: &str,
+    interval: Duration,
+    timeout: Option<Duration>,
+) -> WinResult<Option<Process>> {
+    wait_for_process_with_access(name, Access::PROCESS_ALL_ACCESS, interval, timeout)
+}
+
+/// Waits for a process with the specified name to start, checking every `interval`, and opens it
+/// with the specified access permissions.
+///
+/// Returns `None` if `timeout` elapses first. Waits indefinitely if `timeout` is `None`.
+pub fn wait_for_process_with_access(
+    name: &str,
+    access: Access,
+    interval: Duration,
+    timeout: Option<Duration>,
+) -> WinResult<Option<Process>> {
+    let start = Instant::now();
+    loop {
+        let found = Process::all_with_access(Access::PROCESS_QUERY_LIMITED_INFORMATION)?
+            .find(|p| p.name().map(|n| n == name).unwrap_or(false));
+        if let Some(p) = found {
+            return Process::from_id_with_access(p.id(), access).map(Some);
+        }
+
+        let elapsed = start.elapsed();
+        let sleep = match timeout {
+            Some(timeout) if elapsed >= timeout => return Ok(None),
+            Some(timeout) => interval.min(timeout - elapsed),
+            None => interval,
+        };
+        std::thread::sleep(sleep);
+    }
+}
+
+/// Waits until one of the processes exits, and returns its index in `processes`.
+///
+/// Returns `None` if `timeout` elapses first. Waits indefinitely if `timeout` is `None`. If
+/// several processes have exited, the lowest index is returned.
+///
+/// At most `MAXIMUM_WAIT_OBJECTS` (64) processes can be waited on at once, and more fail with
+/// `ERROR_INVALID_PARAMETER`. Each handle must have the `SYNCHRONIZE` access right.
+pub fn wait_any(processes: &[&Process], timeout: Option<Duration>) -> WinResult<Option<usize>> {
+    wait_multiple(processes, false, timeout)
+}
+
+/// Waits until all of the processes exit.
+///
+/// Returns `WaitResult::TimedOut` if `timeout` elapses first. Waits indefinitely if `timeout` is
+/// `None`.
+///
+/// At most `MAXIMUM_WAIT_OBJECTS` (64) processes can be waited on at once, and more fail with
+/// `ERROR_INVALID_PARAMETER`. Each handle must have the `SYNCHRONIZE` access right.
+pub fn wait_all(processes: &[&Process], timeout: Option<Duration>) -> WinResult<WaitResult> {
+    Ok(match wait_multiple(processes, true, timeout)? {
+        Some(_) => WaitResult::Signaled,
+        None => WaitResult::TimedOut,
+    })
+}
+
+/// The outcome of `wait_all`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum WaitResult {
+    /// All the processes exited.
+    Signaled,
+    /// The timeout elapsed before all the processes exited.
+    TimedOut,
+}
+
+/// The number of 100-nanosecond intervals between January 1, 1601 and the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// Converts a `FILETIME` duration, in 100-nanosecond intervals, to a `Duration`.
+fn filetime_to_duration(time: u64) -> Duration {
+    Duration::from_secs(time / 10_000_000) + Duration::from_nanos(time % 10_000_000 * 100)
+}
+
+/// Converts a `FILETIME` date, in 100-nanosecond intervals since 1601, to a `SystemTime`.
+fn filetime_to_system_time(time: u64) -> SystemTime {
+    if time >= FILETIME_UNIX_EPOCH {
+        UNIX_EPOCH + filetime_to_duration(time - FILETIME_UNIX_EPOCH)
+    } else {
+        UNIX_EPOCH - filetime_to_duration(FILETIME_UNIX_EPOCH - time)
+    }
+}
+
+/// Converts a timeout to milliseconds for the wait functions.
+fn timeout_millis(timeout: Duration) -> DWORD {
+    // `INFINITE` is `u32::MAX`, so longer timeouts are clamped right below it.
+    timeout.as_millis().min(INFINITE as u128 - 1) as DWORD
+}
+
+/// Waits on the handles of the processes with `WaitForMultipleObjects`. Returns the index of the
+/// signaled handle, which is meaningless when waiting for all of them, or `None` on timeout.
+fn wait_multiple(
+    processes: &[&Process],
+    wait_all: bool,
+    timeout: Option<Duration>,
+) -> WinResult<Option<usize>> {
+    if processes.len() > MAXIMUM_WAIT_OBJECTS as usize {
+        return Err(Error::Os(io::Error::from_raw_os_error(
+            ERROR_INVALID_PARAMETER as _,
+        )));
+    }
+    let handles: Vec<winnt::HANDLE> = processes
+        .iter()
+        .map(|p| p.handle.as_raw_handle() as winnt::HANDLE)
+        .collect();
+    let millis = timeout.map_or(INFINITE, timeout_millis);
+    let ret = unsafe {
+        WaitForMultipleObjects(
+            handles.len() as DWORD,
+            handles.as_ptr(),
+            wait_all as _,
+            millis,
+        )
+    };
+    match ret {
+        WAIT_FAILED => Err(Error::last_os_error()),
+        WAIT_TIMEOUT => Ok(None),
+        _ => Ok(Some((ret - WAIT_OBJECT_0) as usize)),
+    }
+}
+
+/// The threads of a process suspended by `Process::suspend`.
+///
+/// Resumes the threads when dropped.
+#[derive(Debug)]
+pub struct SuspendGuard {
+    threads: Vec<(Thread, u32)>,
+}
+
+impl SuspendGuard {
+    /// Returns the suspended threads, each paired with the suspend count it had before.
+    pub fn threads(&self) -> &[(Thread, u32)] {
+        &self.threads
+    }
+
+    /// Resumes the suspended threads, reporting the first failure.
+    pub fn resume(mut self) -> WinResult {
+        self.resume_all()
+    }
+
+    fn resume_all(&mut self) -> WinResult {
+        let mut ret = Ok(());
+        for (thread, _) in self.threads.drain(..) {
+            if let Err(e) = thread.resume() {
+                if ret.is_ok() {
+                    ret = Err(e);
+                }
+            }
+        }
+        ret
+    }
+}
+
+impl Drop for SuspendGuard {
+    fn drop(&mut self) {
+        let _ = self.resume_all();
+    }
 }
 
 impl AsRawHandle for Process {
@@ -493,6 +1912,7 @@ impl IntoRawHandle for Process {
 struct ProcessIter {
     snapshot: Handle,
     access: Access,
+    started: bool,
 }
 
 impl Iterator for ProcessIter {
@@ -500,9 +1920,15 @@ impl Iterator for ProcessIter {
 
     fn next(&mut self) -> Option<WinResult<Process>> {
         unsafe {
-            let mut entry: PROCESSENTRY32 = mem::zeroed();
-            entry.dwSize = mem::size_of::<PROCESSENTRY32>() as DWORD;
-            let ret = Process32Next(self.snapshot.as_raw_handle() as winnt::HANDLE, &mut entry);
+            let mut entry: PROCESSENTRY32W = mem::zeroed();
+            entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as DWORD;
+            let snapshot = self.snapshot.as_raw_handle() as winnt::HANDLE;
+            let ret = if self.started {
+                Process32NextW(snapshot, &mut entry)
+            } else {
+                self.started = true;
+                Process32FirstW(snapshot, &mut entry)
+            };
             if ret == 0 {
                 None
             } else {
@@ -515,6 +1941,195 @@ impl Iterator for ProcessIter {
     }
 }
 
+/// The thresholds of `Process::was_never_resumed_with`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct NeverResumedHeuristic {
+    /// Processes created longer ago than this don't match. Defaults to 30 seconds.
+    pub max_age: Duration,
+    /// Processes with more threads than this don't match. Defaults to 1.
+    pub max_threads: usize,
+}
+
+impl Default for NeverResumedHeuristic {
+    fn default() -> NeverResumedHeuristic {
+        NeverResumedHeuristic {
+            max_age: Duration::from_secs(30),
+            max_threads: 1,
+        }
+    }
+}
+
+/// The GUI objects used by a process, as returned by `Process::gui_object_counts`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct GuiObjectCounts {
+    /// The number of GDI objects in use.
+    pub gdi: u32,
+    /// The number of USER objects in use.
+    pub user: u32,
+    /// The most GDI objects used at once.
+    pub gdi_peak: u32,
+    /// The most USER objects used at once.
+    pub user_peak: u32,
+}
+
+/// Identifies a process across its lifetime, as returned by `Process::identity`.
+///
+/// Process ids are reused once a process exits and all handles to it are closed, but no two
+/// processes share both an id and a creation time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ProcessIdentity {
+    /// The identifier of the process.
+    pub id: u32,
+    /// The creation time of the process, in 100-nanosecond intervals since January 1, 1601 (UTC).
+    pub creation_time: u64,
+}
+
+/// The times of a process, as returned by `Process::times`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ProcessTimes {
+    /// When the process was created.
+    pub creation: SystemTime,
+    /// When the process exited, or `None` if it's still running.
+    pub exit: Option<SystemTime>,
+    /// The CPU time the process has spent in kernel mode, summed over its threads.
+    pub kernel: Duration,
+    /// The CPU time the process has spent in user mode, summed over its threads.
+    pub user: Duration,
+}
+
+/// Holds data related to a running process, listed without opening it.
+///
+/// Maps almost directly to a Windows [PROCESSENTRY32W][PROCESSENTRY32W].
+///
+/// [PROCESSENTRY32W]: https://docs.microsoft.com/en-us/windows/desktop/api/tlhelp32/ns-tlhelp32-processentry32w
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ProcessEntry {
+    /// The identifier of the process.
+    pub id: u32,
+    /// The identifier of the process that created this process. It may have exited since.
+    pub parent_id: u32,
+    /// The number of threads started by the process.
+    pub thread_count: u32,
+    /// The base priority of any threads created by the process.
+    pub base_priority: i32,
+    /// The name of the executable file of the process.
+    pub name: String,
+}
+
+impl ProcessEntry {
+    /// Opens the process. Requests all access.
+    pub fn open(&self) -> WinResult<Process> {
+        Process::from_id(self.id)
+    }
+
+    /// Opens the process. Requests the specified access permissions.
+    pub fn open_with_access(&self, access: Access) -> WinResult<Process> {
+        Process::from_id_with_access(self.id, access)
+    }
+}
+
+impl From<PROCESSENTRY32W> for ProcessEntry {
+    fn from(pe: PROCESSENTRY32W) -> ProcessEntry {
+        let name_end = pe
+            .szExeFile
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(pe.szExeFile.len());
+        ProcessEntry {
+            id: pe.th32ProcessID,
+            parent_id: pe.th32ParentProcessID,
+            thread_count: pe.cntThreads,
+            base_priority: pe.pcPriClassBase,
+            name: String::from_utf16_lossy(&pe.szExeFile[..name_end]),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ProcessEntryIter {
+    snapshot: Handle,
+    started: bool,
+}
+
+impl Iterator for ProcessEntryIter {
+    type Item = ProcessEntry;
+
+    fn next(&mut self) -> Option<ProcessEntry> {
+        unsafe {
+            let mut entry: PROCESSENTRY32W = mem::zeroed();
+            entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as DWORD;
+            let snapshot = self.snapshot.as_raw_handle() as winnt::HANDLE;
+            let ret = if self.started {
+                Process32NextW(snapshot, &mut entry)
+            } else {
+                self.started = true;
+                Process32FirstW(snapshot, &mut entry)
+            };
+            if ret == 0 {
+                None
+            } else {
+                Some(entry.into())
+            }
+        }
+    }
+}
+
+/// The `PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY` attribute, not defined by `winapi`.
+const PROC_THREAD_ATTRIBUTE_GROUP_AFFINITY: DWORD_PTR = 0x0003_0003;
+
+/// An initialized list of thread creation attributes, deleted on drop.
+///
+/// The attribute values aren't copied, so they must outlive the list.
+struct AttributeList {
+    buffer: Vec<u64>,
+}
+
+impl AttributeList {
+    fn new(count: u32) -> WinResult<AttributeList> {
+        unsafe {
+            let mut size = 0;
+            InitializeProcThreadAttributeList(null_mut(), count, 0, &mut size);
+            // The buffer must be pointer-aligned, which `u64` elements guarantee.
+            let mut buffer = vec![0u64; size / mem::size_of::<u64>() + 1];
+            if InitializeProcThreadAttributeList(buffer.as_mut_ptr() as _, count, 0, &mut size) == 0
+            {
+                Err(Error::last_os_error())
+            } else {
+                Ok(AttributeList { buffer })
+            }
+        }
+    }
+
+    fn update(&mut self, attribute: DWORD_PTR, value: *mut c_void, size: usize) -> WinResult {
+        unsafe {
+            let ret = UpdateProcThreadAttribute(
+                self.as_mut_ptr(),
+                0,
+                attribute,
+                value,
+                size,
+                null_mut(),
+                null_mut(),
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> LPPROC_THREAD_ATTRIBUTE_LIST {
+        self.buffer.as_mut_ptr() as _
+    }
+}
+
+impl Drop for AttributeList {
+    fn drop(&mut self) {
+        unsafe { DeleteProcThreadAttributeList(self.as_mut_ptr()) }
+    }
+}
+
 bitflags! {
     /// Windows process-related access permission flags.
     pub struct Access: u32 {
@@ -576,6 +2191,14 @@ impl Default for Access {
     }
 }
 
+bitflags! {
+    /// Flags controlling the creation of a remote thread.
+    pub struct RemoteThreadFlags: u32 {
+        /// The thread is created suspended, and doesn't run until `Thread::resume` is called.
+        const CREATE_SUSPENDED = winbase::CREATE_SUSPENDED;
+    }
+}
+
 /// A process scheduling priority class.
 ///
 /// See [Scheduling Priorities](https://docs.microsoft.com/en-us/windows/desktop/procthread/scheduling-priorities)
@@ -620,6 +2243,173 @@ impl Default for PriorityClass {
     }
 }
 
+/// A processor architecture a process can be built for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Architecture {
+    X86,
+    X64,
+    Arm64,
+}
+
+impl Architecture {
+    fn from_machine(machine: u16) -> Option<Architecture> {
+        match machine {
+            IMAGE_FILE_MACHINE_I386 => Some(Architecture::X86),
+            IMAGE_FILE_MACHINE_AMD64 => Some(Architecture::X64),
+            IMAGE_FILE_MACHINE_ARM64 => Some(Architecture::Arm64),
+            _ => None,
+        }
+    }
+}
+
+/// The I/O counters of a process, as returned by `Process::io_counters`.
+///
+/// Operations other than reads and writes are, for example, device control operations. Counts
+/// include all I/O, not only disk I/O.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct IoCounters {
+    /// The number of read operations.
+    pub read_operation_count: u64,
+    /// The number of write operations.
+    pub write_operation_count: u64,
+    /// The number of other operations.
+    pub other_operation_count: u64,
+    /// The number of bytes read.
+    pub read_transfer_count: u64,
+    /// The number of bytes written.
+    pub write_transfer_count: u64,
+    /// The number of bytes transferred by other operations.
+    pub other_transfer_count: u64,
+}
+
+impl From<IO_COUNTERS> for IoCounters {
+    fn from(io: IO_COUNTERS) -> IoCounters {
+        IoCounters {
+            read_operation_count: io.ReadOperationCount,
+            write_operation_count: io.WriteOperationCount,
+            other_operation_count: io.OtherOperationCount,
+            read_transfer_count: io.ReadTransferCount,
+            write_transfer_count: io.WriteTransferCount,
+            other_transfer_count: io.OtherTransferCount,
+        }
+    }
+}
+
+/// A process I/O priority.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum IoPriority {
+    VeryLow,
+    Low,
+    Normal,
+    High,
+    Critical,
+}
+
+impl IoPriority {
+    fn from_code(code: u32) -> Option<IoPriority> {
+        match code {
+            0 => Some(IoPriority::VeryLow),
+            1 => Some(IoPriority::Low),
+            2 => Some(IoPriority::Normal),
+            3 => Some(IoPriority::High),
+            4 => Some(IoPriority::Critical),
+            _ => None,
+        }
+    }
+
+    fn as_code(&self) -> u32 {
+        match self {
+            IoPriority::VeryLow => 0,
+            IoPriority::Low => 1,
+            IoPriority::Normal => 2,
+            IoPriority::High => 3,
+            IoPriority::Critical => 4,
+        }
+    }
+}
+
+impl Default for IoPriority {
+    fn default() -> IoPriority {
+        IoPriority::Normal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+
+    /// A child process that is killed when dropped, so a failing test doesn't leave it running.
+    struct KillOnDrop(Child);
+
+    impl Drop for KillOnDrop {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    /// Spawns a child process that waits on its standard input until it's killed.
+    fn spawn_paused() -> KillOnDrop {
+        KillOnDrop(
+            Command::new("cmd")
+                .args(["/c", "pause"])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn suspend_guard_restores_prior_suspend_counts() {
+        let child = spawn_paused();
+        let process = Process::from_id(child.0.id()).unwrap();
+        let thread = process.threads().unwrap().next().unwrap();
+        thread.suspend().unwrap();
+        thread.suspend().unwrap();
+
+        let guard = process.suspend().unwrap();
+        assert!(guard
+            .threads()
+            .iter()
+            .any(|(t, count)| t.id() == thread.id() && *count == 2));
+        drop(guard);
+
+        // The thread was suspended twice before the guard, so it must still be.
+        assert_eq!(thread.suspend().unwrap(), 2);
+    }
+
+    #[test]
+    fn lists_modules_of_current_process() {
+        let process = Process::current();
+        assert!(!process.module_list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_trips_priority_classes() {
+        // A child is used so that the test runner, shared with the other tests, keeps its class.
+        let child = spawn_paused();
+        let mut process = Process::from_id(child.0.id()).unwrap();
+        let classes = [
+            PriorityClass::Idle,
+            PriorityClass::BelowNormal,
+            PriorityClass::Normal,
+            PriorityClass::AboveNormal,
+            PriorityClass::High,
+        ];
+        for &class in &classes {
+            process.set_priority(class).unwrap();
+            assert_eq!(process.priority().unwrap(), class);
+        }
+
+        // Without `SeIncreaseBasePriorityPrivilege`, requesting realtime silently yields high.
+        process.set_priority(PriorityClass::Realtime).unwrap();
+        let class = process.priority().unwrap();
+        assert!(class == PriorityClass::Realtime || class == PriorityClass::High);
+    }
+}
+
 //mod tests {
 //    #[allow(unused_imports)]
 //    use super::*;
@@ -1,5 +1,7 @@
-use crate::{Error, Handle, Process, WinResult};
+use super::pe::RemoteImage;
+use crate::{wintrust, Error, Handle, Process, WinResult};
 use std::{
+    collections::HashMap,
     ffi::{CString, OsString},
     mem,
     os::windows::{io::AsRawHandle, prelude::*},
@@ -11,8 +13,8 @@ use winapi::{
     um::{
         libloaderapi::GetProcAddress,
         psapi::{GetModuleBaseNameW, GetModuleFileNameExW, GetModuleInformation, MODULEINFO},
-        tlhelp32::{Module32NextW, MODULEENTRY32W},
-        winnt::{self, WCHAR},
+        tlhelp32::{Module32FirstW, Module32NextW, MODULEENTRY32W},
+        winnt::{self, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ, IMAGE_SCN_MEM_WRITE, WCHAR},
     },
 };
 
@@ -85,6 +87,79 @@ impl<'a> Module<'a> {
         }
     }
 
+    /// Returns the modules and functions imported by the module, as listed in its import directory.
+    ///
+    /// Functions imported by images bound without an import name table can't be named, so the
+    /// function list of such modules is empty.
+    ///
+    /// The process handle must have the `PROCESS_VM_READ` access right.
+    pub fn imports(&self) -> WinResult<Vec<ImportedModule>> {
+        RemoteImage::new(self.process, self.handle as usize)?.imports()
+    }
+
+    /// Returns the modules and functions delay-loaded by the module.
+    ///
+    /// The process handle must have the `PROCESS_VM_READ` access right.
+    pub fn delay_imports(&self) -> WinResult<Vec<ImportedModule>> {
+        RemoteImage::new(self.process, self.handle as usize)?.delay_imports()
+    }
+
+    /// Returns true if the file of the module has a valid Authenticode signature, either embedded
+    /// in it or in a catalog registered on the system, as most Windows system files have.
+    ///
+    /// The signature of the file on disk is checked, not the mapped image. Certificate revocation
+    /// isn't checked.
+    pub fn is_signed(&self) -> WinResult<bool> {
+        wintrust::verify_file(&self.path()?)
+    }
+
+    /// Returns the PDB information from the CodeView record of the module's debug directory,
+    /// which is what symbol servers index PDBs by.
+    ///
+    /// Returns `None` if the module has no debug directory, no PDB 7.0 record, or if the record
+    /// isn't mapped within the image.
+    ///
+    /// The process handle must have the `PROCESS_VM_READ` access right.
+    pub fn debug_info(&self) -> WinResult<Option<DebugInfo>> {
+        RemoteImage::new(self.process, self.handle as usize)?.debug_info()
+    }
+
+    /// Lists the sections of the module, as described by the section table of its image.
+    ///
+    /// The process handle must have the `PROCESS_VM_READ` access right.
+    pub fn sections(&self) -> WinResult<Vec<Section>> {
+        RemoteImage::new(self.process, self.handle as usize)?.sections()
+    }
+
+    /// Returns true if a section of the module is readable, writable and executable at once,
+    /// which legitimate compilers don't emit but packers and injected code often do.
+    ///
+    /// Only the section table is checked, so pages whose protection was changed after the module
+    /// was loaded aren't detected.
+    ///
+    /// The process handle must have the `PROCESS_VM_READ` access right.
+    pub fn has_rwx_section(&self) -> WinResult<bool> {
+        Ok(self.sections()?.iter().any(Section::is_rwx))
+    }
+
+    /// Builds a module entry for the module, as if it was listed by `Process::module_entries`.
+    ///
+    /// The load counts aren't available outside of a snapshot, so they are set to `0xffff`.
+    pub fn to_entry(&self) -> WinResult<ModuleEntry> {
+        let info = self.info()?;
+        Ok(ModuleEntry {
+            id: 1,
+            name: self.name()?,
+            path: self.path()?,
+            hmodule: self.handle,
+            process_id: self.process.id(),
+            global_load_count: 0xffff,
+            proc_load_count: 0xffff,
+            mod_base_addr: info.address as *mut u8,
+            mod_base_size: info.size as u32,
+        })
+    }
+
     /// Returns a void pointer to the function in the module with the specified name.
     pub fn proc_address(&self, proc_name: &str) -> WinResult<*mut c_void> {
         unsafe {
@@ -120,6 +195,101 @@ impl From<MODULEINFO> for ModuleInfo {
     }
 }
 
+/// A module listed in the loader data of a process, as returned by `Process::modules_ordered`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OrderedModule {
+    /// The base address of the module.
+    pub base: usize,
+    /// The size of the module's image, in bytes.
+    pub size: usize,
+    /// The fully qualified path to the module's file.
+    pub path: PathBuf,
+    /// The `TimeDateStamp` of the module's PE file header, usually its link time in seconds since
+    /// the Unix epoch. `None` if the header couldn't be read.
+    pub time_date_stamp: Option<u32>,
+}
+
+/// The PDB information of a module, as returned by `Module::debug_info`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DebugInfo {
+    /// The path to the PDB file, as recorded by the linker.
+    pub pdb_path: String,
+    /// The GUID identifying the PDB, in its in-memory layout.
+    pub guid: [u8; 16],
+    /// The age of the PDB, incremented each time it's updated.
+    pub age: u32,
+}
+
+impl DebugInfo {
+    /// Returns the identifier of the PDB on a symbol server: its GUID followed by its age, as
+    /// uppercase hexadecimal.
+    pub fn symbol_server_id(&self) -> String {
+        let g = &self.guid;
+        let data1 = u32::from_le_bytes([g[0], g[1], g[2], g[3]]);
+        let data2 = u16::from_le_bytes([g[4], g[5]]);
+        let data3 = u16::from_le_bytes([g[6], g[7]]);
+        let mut id = format!("{:08X}{:04X}{:04X}", data1, data2, data3);
+        for byte in &g[8..] {
+            id.push_str(&format!("{:02X}", byte));
+        }
+        id.push_str(&format!("{:X}", self.age));
+        id
+    }
+}
+
+/// A section of a module's image, as returned by `Module::sections`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Section {
+    /// The name of the section, such as `.text`.
+    pub name: String,
+    /// The address of the section, relative to the base of the module.
+    pub virtual_address: u32,
+    /// The size of the section once loaded, in bytes.
+    pub virtual_size: u32,
+    /// The `IMAGE_SCN_*` characteristics flags of the section.
+    pub characteristics: u32,
+}
+
+impl Section {
+    /// Returns true if the section is mapped readable.
+    pub fn is_readable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_READ != 0
+    }
+
+    /// Returns true if the section is mapped writable.
+    pub fn is_writable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_WRITE != 0
+    }
+
+    /// Returns true if the section is mapped executable.
+    pub fn is_executable(&self) -> bool {
+        self.characteristics & IMAGE_SCN_MEM_EXECUTE != 0
+    }
+
+    /// Returns true if the section is readable, writable and executable.
+    pub fn is_rwx(&self) -> bool {
+        self.is_readable() && self.is_writable() && self.is_executable()
+    }
+}
+
+/// A module imported by another module, along with the functions imported from it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ImportedModule {
+    /// The name of the imported module, as written in the importing image.
+    pub name: String,
+    /// The functions imported from the module.
+    pub functions: Vec<ImportedFunction>,
+}
+
+/// A function imported from a module.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum ImportedFunction {
+    /// A function imported by name.
+    Name(String),
+    /// A function imported by ordinal.
+    Ordinal(u16),
+}
+
 /// Holds data related to a module of a running process.
 ///
 /// Maps almost directly to a Windows [MODULEENTRY32W][MODULEENTRY32W].
@@ -149,6 +319,26 @@ pub struct ModuleEntry {
     pub mod_base_size: u32,
 }
 
+impl ModuleEntry {
+    /// Returns a handle to the module in the specified process, which should be the process
+    /// the entry was listed from.
+    pub fn to_module<'a>(&self, process: &'a Process) -> Module<'a> {
+        Module {
+            handle: self.hmodule,
+            process,
+        }
+    }
+}
+
+impl PartialEq for ModuleEntry {
+    /// Two entries are equal if they refer to the same module handle in the same process.
+    fn eq(&self, other: &ModuleEntry) -> bool {
+        self.process_id == other.process_id && self.hmodule == other.hmodule
+    }
+}
+
+impl Eq for ModuleEntry {}
+
 impl From<MODULEENTRY32W> for ModuleEntry {
     fn from(me: MODULEENTRY32W) -> ModuleEntry {
         let name_end = me
@@ -181,10 +371,72 @@ impl From<MODULEENTRY32W> for ModuleEntry {
     }
 }
 
+/// The differences between the modules loaded in two processes, as returned by `module_diff`.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleDiff {
+    /// The modules only loaded in the first process.
+    pub only_in_a: Vec<ModuleEntry>,
+    /// The modules only loaded in the second process.
+    pub only_in_b: Vec<ModuleEntry>,
+    /// The modules loaded in both processes, but at different base addresses, as pairs of the
+    /// entries of the first and second process.
+    pub different_base: Vec<(ModuleEntry, ModuleEntry)>,
+}
+
+impl ModuleDiff {
+    /// Returns true if both processes have the same modules loaded at the same addresses.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.different_base.is_empty()
+    }
+}
+
+/// Compares the modules loaded in two processes.
+///
+/// Modules are matched by path, ignoring case, or by name when toolhelp doesn't report a path.
+/// Entries are listed in the order they were listed by `Process::module_entries`.
+///
+/// Both handles must have the `PROCESS_QUERY_INFORMATION` and `PROCESS_VM_READ` access rights.
+pub fn module_diff(a: &Process, b: &Process) -> WinResult<ModuleDiff> {
+    fn key(entry: &ModuleEntry) -> String {
+        if entry.path.as_os_str().is_empty() {
+            entry.name.to_lowercase()
+        } else {
+            entry.path.to_string_lossy().to_lowercase()
+        }
+    }
+
+    let b_entries: Vec<ModuleEntry> = b.module_entries()?.collect();
+    let mut unmatched: HashMap<String, usize> = b_entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (key(entry), i))
+        .collect();
+
+    let mut diff = ModuleDiff::default();
+    for a_entry in a.module_entries()? {
+        match unmatched.remove(&key(&a_entry)) {
+            Some(i) => {
+                if a_entry.mod_base_addr != b_entries[i].mod_base_addr {
+                    diff.different_base.push((a_entry, b_entries[i].clone()));
+                }
+            }
+            None => diff.only_in_a.push(a_entry),
+        }
+    }
+    diff.only_in_b = b_entries
+        .into_iter()
+        .enumerate()
+        .filter(|(i, entry)| unmatched.get(&key(entry)) == Some(i))
+        .map(|(_, entry)| entry)
+        .collect();
+    Ok(diff)
+}
+
 #[derive(Debug)]
 pub struct ModuleEntryIter<'a> {
     pub(crate) process: &'a Process,
     pub(crate) snapshot: Handle,
+    pub(crate) started: bool,
 }
 
 impl<'a> Iterator for ModuleEntryIter<'a> {
@@ -194,11 +446,24 @@ impl<'a> Iterator for ModuleEntryIter<'a> {
         unsafe {
             let mut entry: MODULEENTRY32W = mem::zeroed();
             entry.dwSize = mem::size_of::<MODULEENTRY32W>() as DWORD;
-            let ret = Module32NextW(self.snapshot.as_raw_handle() as winnt::HANDLE, &mut entry);
+            let snapshot = self.snapshot.as_raw_handle() as winnt::HANDLE;
+            let ret = if self.started {
+                Module32NextW(snapshot, &mut entry)
+            } else {
+                self.started = true;
+                Module32FirstW(snapshot, &mut entry)
+            };
             if ret == 0 {
                 None
             } else {
-                Some(entry.into())
+                let mut entry: ModuleEntry = entry.into();
+                // Toolhelp leaves the path of some modules of WOW64 processes blank.
+                if entry.path.as_os_str().is_empty() {
+                    if let Ok(path) = entry.to_module(self.process).path() {
+                        entry.path = path;
+                    }
+                }
+                Some(entry)
             }
         }
     }
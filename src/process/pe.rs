@@ -0,0 +1,540 @@
+//! Parsing of PE images mapped in the memory of another process.
+
+use super::module::{DebugInfo, ImportedFunction, ImportedModule, Section};
+use crate::{Error, Process, WinResult};
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    mem,
+    path::Path,
+    slice,
+};
+use winapi::{
+    shared::winerror::{ERROR_BAD_EXE_FORMAT, ERROR_PROC_NOT_FOUND},
+    um::winnt::{
+        IMAGE_DATA_DIRECTORY,
+        IMAGE_DEBUG_DIRECTORY,
+        IMAGE_DEBUG_TYPE_CODEVIEW,
+        IMAGE_DELAYLOAD_DESCRIPTOR,
+        IMAGE_DIRECTORY_ENTRY_DEBUG,
+        IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT,
+        IMAGE_DIRECTORY_ENTRY_EXPORT,
+        IMAGE_DIRECTORY_ENTRY_IMPORT,
+        IMAGE_DOS_HEADER,
+        IMAGE_DOS_SIGNATURE,
+        IMAGE_EXPORT_DIRECTORY,
+        IMAGE_FILE_DLL,
+        IMAGE_FILE_HEADER,
+        IMAGE_IMPORT_DESCRIPTOR,
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC,
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC,
+        IMAGE_NT_SIGNATURE,
+        IMAGE_NUMBEROF_DIRECTORY_ENTRIES,
+        IMAGE_OPTIONAL_HEADER32,
+        IMAGE_OPTIONAL_HEADER64,
+        IMAGE_ORDINAL_FLAG32,
+        IMAGE_ORDINAL_FLAG64,
+        IMAGE_SECTION_HEADER,
+        IMAGE_SUBSYSTEM_WINDOWS_CUI,
+        IMAGE_SUBSYSTEM_WINDOWS_GUI,
+    },
+};
+
+/// The longest import or export name that will be read from an image.
+const MAX_NAME_LEN: usize = 1024;
+/// The most entries that will be read from a zero-terminated table, in case it's corrupt.
+const MAX_TABLE_LEN: u32 = 0x10000;
+/// The signature of a CodeView PDB 7.0 debug record, `RSDS`.
+const CODEVIEW_RSDS_SIGNATURE: u32 = 0x5344_5352;
+
+/// The fixed-size part of a CodeView PDB 7.0 debug record, followed by the PDB path.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CodeViewRsds {
+    signature: u32,
+    guid: [u8; 16],
+    age: u32,
+}
+
+/// The main fields of the headers of a PE file, as returned by `Process::image_pe_headers`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PeHeaders {
+    /// The target architecture of the image, one of the `IMAGE_FILE_MACHINE_*` values.
+    pub machine: u16,
+    /// The `IMAGE_FILE_*` characteristics flags of the image.
+    pub characteristics: u16,
+    /// The link time of the image, in seconds since the Unix epoch, or a hash of the image for
+    /// reproducible builds.
+    pub time_date_stamp: u32,
+    /// Whether the image has a 64-bit (PE32+) optional header.
+    pub pe64: bool,
+    /// The preferred base address of the image.
+    pub image_base: u64,
+    /// The size of the image once loaded, in bytes.
+    pub size_of_image: u32,
+    /// The relative virtual address of the entry point, or zero if there's none.
+    pub entry_point: u32,
+    /// The subsystem required to run the image, one of the `IMAGE_SUBSYSTEM_*` values.
+    pub subsystem: u16,
+    /// The `IMAGE_DLLCHARACTERISTICS_*` flags of the image.
+    pub dll_characteristics: u16,
+}
+
+impl PeHeaders {
+    /// Reads the headers of the PE file at the specified path.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> WinResult<PeHeaders> {
+        let mut file = File::open(path)?;
+        let dos: IMAGE_DOS_HEADER = read_file_value(&mut file)?;
+        if dos.e_magic != IMAGE_DOS_SIGNATURE {
+            return Err(bad_format());
+        }
+        file.seek(SeekFrom::Start(dos.e_lfanew as u64))?;
+        let signature: u32 = read_file_value(&mut file)?;
+        if signature != IMAGE_NT_SIGNATURE {
+            return Err(bad_format());
+        }
+        let file_header: IMAGE_FILE_HEADER = read_file_value(&mut file)?;
+
+        let optional = dos.e_lfanew as u64
+            + (mem::size_of::<u32>() + mem::size_of::<IMAGE_FILE_HEADER>()) as u64;
+        let magic: u16 = read_file_value(&mut file)?;
+        file.seek(SeekFrom::Start(optional))?;
+        let pe64 = match magic {
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => false,
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => true,
+            _ => return Err(bad_format()),
+        };
+        let (image_base, size_of_image, entry_point, subsystem, dll_characteristics) = if pe64 {
+            let header: IMAGE_OPTIONAL_HEADER64 = read_file_value(&mut file)?;
+            (
+                header.ImageBase,
+                header.SizeOfImage,
+                header.AddressOfEntryPoint,
+                header.Subsystem,
+                header.DllCharacteristics,
+            )
+        } else {
+            let header: IMAGE_OPTIONAL_HEADER32 = read_file_value(&mut file)?;
+            (
+                header.ImageBase as u64,
+                header.SizeOfImage,
+                header.AddressOfEntryPoint,
+                header.Subsystem,
+                header.DllCharacteristics,
+            )
+        };
+
+        Ok(PeHeaders {
+            machine: file_header.Machine,
+            characteristics: file_header.Characteristics,
+            time_date_stamp: file_header.TimeDateStamp,
+            pe64,
+            image_base,
+            size_of_image,
+            entry_point,
+            subsystem,
+            dll_characteristics,
+        })
+    }
+
+    /// Returns true if the image is a DLL rather than an executable.
+    pub fn is_dll(&self) -> bool {
+        self.characteristics & IMAGE_FILE_DLL != 0
+    }
+
+    /// Returns true if the image runs in the Windows GUI subsystem.
+    pub fn is_gui(&self) -> bool {
+        self.subsystem == IMAGE_SUBSYSTEM_WINDOWS_GUI
+    }
+
+    /// Returns true if the image runs in the Windows console subsystem.
+    pub fn is_console(&self) -> bool {
+        self.subsystem == IMAGE_SUBSYSTEM_WINDOWS_CUI
+    }
+}
+
+/// Reads a plain-old-data value at the current position of the file.
+fn read_file_value<T: Copy>(file: &mut File) -> WinResult<T> {
+    unsafe {
+        let mut value: T = mem::zeroed();
+        let buf = slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, mem::size_of::<T>());
+        file.read_exact(buf).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                bad_format()
+            } else {
+                e.into()
+            }
+        })?;
+        Ok(value)
+    }
+}
+
+/// A PE image loaded in a (possibly remote) process.
+pub(crate) struct RemoteImage<'a> {
+    process: &'a Process,
+    base: usize,
+    pe64: bool,
+    image_base: u64,
+    size_of_image: u32,
+    time_date_stamp: u32,
+    directories: [IMAGE_DATA_DIRECTORY; IMAGE_NUMBEROF_DIRECTORY_ENTRIES],
+    section_headers: u32,
+    number_of_sections: u16,
+}
+
+impl<'a> RemoteImage<'a> {
+    /// Reads the headers of the image loaded at `base` in `process`.
+    pub fn new(process: &'a Process, base: usize) -> WinResult<RemoteImage<'a>> {
+        let dos: IMAGE_DOS_HEADER = process.read_value(base)?;
+        if dos.e_magic != IMAGE_DOS_SIGNATURE {
+            return Err(bad_format());
+        }
+        if dos.e_lfanew < 0 {
+            return Err(bad_format());
+        }
+        let nt = base
+            .checked_add(dos.e_lfanew as usize)
+            .ok_or_else(bad_format)?;
+        let signature: u32 = process.read_value(nt)?;
+        if signature != IMAGE_NT_SIGNATURE {
+            return Err(bad_format());
+        }
+
+        let file: IMAGE_FILE_HEADER = process.read_value(nt + mem::size_of::<u32>())?;
+        let optional = nt + mem::size_of::<u32>() + mem::size_of::<IMAGE_FILE_HEADER>();
+        let magic: u16 = process.read_value(optional)?;
+        let pe64 = match magic {
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => false,
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => true,
+            _ => return Err(bad_format()),
+        };
+        let (image_base, size_of_image, count, mut directories) = if pe64 {
+            let header: IMAGE_OPTIONAL_HEADER64 = process.read_value(optional)?;
+            (
+                header.ImageBase,
+                header.SizeOfImage,
+                header.NumberOfRvaAndSizes,
+                header.DataDirectory,
+            )
+        } else {
+            let header: IMAGE_OPTIONAL_HEADER32 = process.read_value(optional)?;
+            (
+                header.ImageBase as u64,
+                header.SizeOfImage,
+                header.NumberOfRvaAndSizes,
+                header.DataDirectory,
+            )
+        };
+        for directory in directories.iter_mut().skip(count as usize) {
+            directory.VirtualAddress = 0;
+            directory.Size = 0;
+        }
+
+        Ok(RemoteImage {
+            process,
+            base,
+            pe64,
+            image_base,
+            size_of_image,
+            time_date_stamp: file.TimeDateStamp,
+            directories,
+            section_headers: (optional - base) as u32 + file.SizeOfOptionalHeader as u32,
+            number_of_sections: file.NumberOfSections,
+        })
+    }
+
+    /// Returns the link time of the image, in seconds since the Unix epoch.
+    ///
+    /// Reproducible builds store a hash of the image here instead.
+    pub fn time_date_stamp(&self) -> u32 {
+        self.time_date_stamp
+    }
+
+    /// Lists the sections of the image, from its section table.
+    pub fn sections(&self) -> WinResult<Vec<Section>> {
+        let headers: Vec<IMAGE_SECTION_HEADER> =
+            self.read_array(self.section_headers, self.number_of_sections as usize)?;
+        Ok(headers
+            .iter()
+            .map(|header| {
+                // The name is padded with nuls, and only terminated if shorter than 8 bytes.
+                let len = header
+                    .Name
+                    .iter()
+                    .position(|&b| b == 0)
+                    .unwrap_or(header.Name.len());
+                Section {
+                    name: String::from_utf8_lossy(&header.Name[..len]).into_owned(),
+                    virtual_address: header.VirtualAddress,
+                    virtual_size: unsafe { *header.Misc.VirtualSize() },
+                    characteristics: header.Characteristics,
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the data directory at the specified index, if the image has one.
+    pub fn directory(&self, index: u16) -> Option<IMAGE_DATA_DIRECTORY> {
+        self.directories
+            .get(index as usize)
+            .cloned()
+            .filter(|d| d.VirtualAddress != 0 && d.Size != 0)
+    }
+
+    /// Reads a plain-old-data value at the relative virtual address.
+    pub fn read<T: Copy>(&self, rva: u32) -> WinResult<T> {
+        self.process.read_value(self.base + rva as usize)
+    }
+
+    /// Reads a plain-old-data value at `offset` bytes past the relative virtual address.
+    pub fn read_at<T: Copy>(&self, rva: u32, offset: u32) -> WinResult<T> {
+        self.read(add_rva(rva, offset)?)
+    }
+
+    /// Reads `count` consecutive values starting at the relative virtual address.
+    pub fn read_array<T: Copy>(&self, rva: u32, count: usize) -> WinResult<Vec<T>> {
+        unsafe {
+            let mut values: Vec<T> = vec![mem::zeroed(); count];
+            let buf = slice::from_raw_parts_mut(
+                values.as_mut_ptr() as *mut u8,
+                count * mem::size_of::<T>(),
+            );
+            self.process.read_exact(self.base + rva as usize, buf)?;
+            Ok(values)
+        }
+    }
+
+    /// Reads a nul-terminated ANSI string at the relative virtual address.
+    pub fn read_c_string(&self, rva: u32) -> WinResult<String> {
+        let mut bytes = Vec::new();
+        let mut chunk = [0u8; 64];
+        while bytes.len() < MAX_NAME_LEN {
+            let address = self.base + rva as usize + bytes.len();
+            // The string may end right before an unreadable page, so settle for a short read.
+            let read = self
+                .process
+                .read_memory(address, &mut chunk)
+                .or_else(|_| self.process.read_exact(address, &mut chunk[..1]).map(|_| 1))?;
+            if let Some(end) = chunk[..read].iter().position(|&b| b == 0) {
+                bytes.extend_from_slice(&chunk[..end]);
+                break;
+            }
+            bytes.extend_from_slice(&chunk[..read]);
+        }
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Finds the address of the exported function with the specified name.
+    pub fn export(&self, name: &str) -> WinResult<Export> {
+        let dir = self
+            .directory(IMAGE_DIRECTORY_ENTRY_EXPORT)
+            .ok_or_else(proc_not_found)?;
+        let exports: IMAGE_EXPORT_DIRECTORY = self.read(dir.VirtualAddress)?;
+
+        // Ordinals are 16-bit, so a larger count can only come from a corrupt directory.
+        if exports.NumberOfNames > MAX_TABLE_LEN {
+            return Err(bad_format());
+        }
+        // Names are sorted, so a binary search keeps the number of remote reads small.
+        let names: Vec<u32> =
+            self.read_array(exports.AddressOfNames, exports.NumberOfNames as _)?;
+        let (mut low, mut high) = (0, names.len());
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.read_c_string(names[mid])?.as_str().cmp(name) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    let index: u16 = self.read_at(exports.AddressOfNameOrdinals, mid as u32 * 2)?;
+                    return self.export_at(&dir, &exports, index as u32);
+                }
+            }
+        }
+        Err(proc_not_found())
+    }
+
+    /// Finds the address of the function exported with the specified ordinal.
+    pub fn export_by_ordinal(&self, ordinal: u32) -> WinResult<Export> {
+        let dir = self
+            .directory(IMAGE_DIRECTORY_ENTRY_EXPORT)
+            .ok_or_else(proc_not_found)?;
+        let exports: IMAGE_EXPORT_DIRECTORY = self.read(dir.VirtualAddress)?;
+        match ordinal.checked_sub(exports.Base) {
+            Some(index) if index < exports.NumberOfFunctions => {
+                self.export_at(&dir, &exports, index)
+            }
+            _ => Err(proc_not_found()),
+        }
+    }
+
+    /// Reads the entry at `index` of the export address table.
+    fn export_at(
+        &self,
+        dir: &IMAGE_DATA_DIRECTORY,
+        exports: &IMAGE_EXPORT_DIRECTORY,
+        index: u32,
+    ) -> WinResult<Export> {
+        // Ordinals are 16-bit, which also keeps the table offset from overflowing.
+        if index >= exports.NumberOfFunctions.min(MAX_TABLE_LEN) {
+            return Err(bad_format());
+        }
+        let rva: u32 = self.read_at(exports.AddressOfFunctions, index * 4)?;
+        if rva == 0 {
+            return Err(proc_not_found());
+        }
+        // An address inside the export directory is a forwarder string "DLL.Function".
+        if rva >= dir.VirtualAddress && rva - dir.VirtualAddress < dir.Size {
+            Ok(Export::Forwarded(self.read_c_string(rva)?))
+        } else {
+            Ok(Export::Address(self.base + rva as usize))
+        }
+    }
+
+    /// Lists the modules and functions in the import directory.
+    pub fn imports(&self) -> WinResult<Vec<ImportedModule>> {
+        let dir = match self.directory(IMAGE_DIRECTORY_ENTRY_IMPORT) {
+            Some(dir) => dir,
+            None => return Ok(Vec::new()),
+        };
+        let size = mem::size_of::<IMAGE_IMPORT_DESCRIPTOR>() as u32;
+        let mut modules = Vec::new();
+        for i in 0..(dir.Size / size).min(MAX_TABLE_LEN) {
+            let desc: IMAGE_IMPORT_DESCRIPTOR = self.read_at(dir.VirtualAddress, i * size)?;
+            if desc.Name == 0 {
+                break;
+            }
+            // Images bound without a name table only keep the resolved addresses, whose names
+            // can't be recovered.
+            let names = unsafe { *desc.u.OriginalFirstThunk() };
+            modules.push(ImportedModule {
+                name: self.read_c_string(desc.Name)?,
+                functions: if names == 0 {
+                    Vec::new()
+                } else {
+                    self.read_thunks(names)?
+                },
+            });
+        }
+        Ok(modules)
+    }
+
+    /// Lists the modules and functions in the delay-load import directory.
+    pub fn delay_imports(&self) -> WinResult<Vec<ImportedModule>> {
+        let dir = match self.directory(IMAGE_DIRECTORY_ENTRY_DELAY_IMPORT) {
+            Some(dir) => dir,
+            None => return Ok(Vec::new()),
+        };
+        let size = mem::size_of::<IMAGE_DELAYLOAD_DESCRIPTOR>() as u32;
+        let mut modules = Vec::new();
+        for i in 0..(dir.Size / size).min(MAX_TABLE_LEN) {
+            let desc: IMAGE_DELAYLOAD_DESCRIPTOR = self.read_at(dir.VirtualAddress, i * size)?;
+            if desc.DllNameRVA == 0 {
+                break;
+            }
+            // Descriptors produced by old linkers hold virtual addresses rather than RVAs.
+            let rva_based = desc.Attributes.AllAttributes & 1 != 0;
+            let to_rva = |address: u32| {
+                if rva_based {
+                    address
+                } else {
+                    (address as u64).wrapping_sub(self.image_base) as u32
+                }
+            };
+            modules.push(ImportedModule {
+                name: self.read_c_string(to_rva(desc.DllNameRVA))?,
+                functions: if desc.ImportNameTableRVA == 0 {
+                    Vec::new()
+                } else {
+                    self.read_thunks(to_rva(desc.ImportNameTableRVA))?
+                },
+            });
+        }
+        Ok(modules)
+    }
+
+    /// Finds the CodeView PDB 7.0 record in the debug directory.
+    pub fn debug_info(&self) -> WinResult<Option<DebugInfo>> {
+        let dir = match self.directory(IMAGE_DIRECTORY_ENTRY_DEBUG) {
+            Some(dir) if self.contains(dir.VirtualAddress, dir.Size) => dir,
+            _ => return Ok(None),
+        };
+        let size = mem::size_of::<IMAGE_DEBUG_DIRECTORY>() as u32;
+        let entries: Vec<IMAGE_DEBUG_DIRECTORY> = self.read_array(
+            dir.VirtualAddress,
+            (dir.Size / size).min(MAX_TABLE_LEN) as usize,
+        )?;
+        for entry in entries {
+            // Debug data that isn't mapped, or that claims to lie outside of the image, can't be
+            // read from memory.
+            if entry.Type != IMAGE_DEBUG_TYPE_CODEVIEW
+                || (entry.SizeOfData as usize) < mem::size_of::<CodeViewRsds>()
+                || !self.contains(entry.AddressOfRawData, entry.SizeOfData)
+            {
+                continue;
+            }
+            let record: CodeViewRsds = self.read(entry.AddressOfRawData)?;
+            if record.signature != CODEVIEW_RSDS_SIGNATURE {
+                continue;
+            }
+            let path = entry.AddressOfRawData + mem::size_of::<CodeViewRsds>() as u32;
+            return Ok(Some(DebugInfo {
+                pdb_path: self.read_c_string(path)?,
+                guid: record.guid,
+                age: record.age,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Returns true if the range of relative virtual addresses is non-empty and lies within
+    /// the image.
+    fn contains(&self, rva: u32, size: u32) -> bool {
+        rva != 0 && size != 0 && rva as u64 + size as u64 <= self.size_of_image as u64
+    }
+
+    /// Reads a zero-terminated import name table.
+    fn read_thunks(&self, rva: u32) -> WinResult<Vec<ImportedFunction>> {
+        let mut functions = Vec::new();
+        for i in 0..MAX_TABLE_LEN {
+            let (thunk, by_ordinal) = if self.pe64 {
+                let thunk: u64 = self.read_at(rva, i * 8)?;
+                (thunk, thunk & IMAGE_ORDINAL_FLAG64 != 0)
+            } else {
+                let thunk: u32 = self.read_at(rva, i * 4)?;
+                (thunk as u64, thunk & IMAGE_ORDINAL_FLAG32 != 0)
+            };
+            if thunk == 0 {
+                break;
+            }
+            functions.push(if by_ordinal {
+                ImportedFunction::Ordinal(thunk as u16)
+            } else {
+                // Skip the 2-byte hint of the IMAGE_IMPORT_BY_NAME.
+                ImportedFunction::Name(self.read_c_string(add_rva(thunk as u32, 2)?)?)
+            });
+        }
+        Ok(functions)
+    }
+}
+
+/// The location of an exported function.
+pub(crate) enum Export {
+    /// The absolute address of the function.
+    Address(usize),
+    /// The function is forwarded to another module, in the form `DLL.Function` or `DLL.#Ordinal`.
+    Forwarded(String),
+}
+
+/// Adds an offset to a relative virtual address read from the image, which may be corrupt.
+fn add_rva(rva: u32, offset: u32) -> WinResult<u32> {
+    rva.checked_add(offset).ok_or_else(bad_format)
+}
+
+pub(crate) fn bad_format() -> Error {
+    Error::Os(io::Error::from_raw_os_error(ERROR_BAD_EXE_FORMAT as _))
+}
+
+fn proc_not_found() -> Error {
+    Error::Os(io::Error::from_raw_os_error(ERROR_PROC_NOT_FOUND as _))
+}
@@ -0,0 +1,469 @@
+use super::{module::OrderedModule, pe::RemoteImage};
+use crate::{ntdll, Error, Process, WinResult};
+use std::{
+    collections::HashMap,
+    ffi::{OsStr, OsString},
+    io,
+    mem,
+    os::windows::prelude::*,
+    path::PathBuf,
+    slice,
+};
+use widestring::{WideCStr, WideCString};
+use winapi::{
+    shared::{ntdef::UNICODE_STRING, winerror::ERROR_BAD_LENGTH},
+    um::{shellapi::CommandLineToArgvW, winbase::LocalFree},
+};
+
+/// The most loader entries that will be followed, in case the list is corrupt.
+const MAX_LOADER_ENTRIES: usize = 0x10000;
+/// The most heaps that will be read, in case the heap count is corrupt.
+const MAX_HEAPS: u32 = 0x10000;
+/// The largest environment block that will be read, in case its size is corrupt.
+const MAX_ENVIRONMENT_SIZE: usize = 0x100_0000;
+
+/// The `STARTUPINFO` fields of `RTL_USER_PROCESS_PARAMETERS`, which have the same layout for
+/// every bitness.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct StartupFields {
+    starting_x: u32,
+    starting_y: u32,
+    count_x: u32,
+    count_y: u32,
+    count_chars_x: u32,
+    count_chars_y: u32,
+    fill_attribute: u32,
+    window_flags: u32,
+    show_window_flags: u32,
+}
+
+/// The offsets of the fields read from a process environment block and the structures it points
+/// to, which depend on its bitness.
+struct PebLayout {
+    pointer_size: usize,
+    process_parameters: usize,
+    process_heap: usize,
+    number_of_heaps: usize,
+    process_heaps: usize,
+    /// The offset of `CurrentDirectory.DosPath` in `RTL_USER_PROCESS_PARAMETERS`.
+    current_directory: usize,
+    /// The offset of `ImagePathName` in `RTL_USER_PROCESS_PARAMETERS`.
+    image_path_name: usize,
+    /// The offset of `CommandLine` in `RTL_USER_PROCESS_PARAMETERS`.
+    command_line: usize,
+    /// The offset of `Environment` in `RTL_USER_PROCESS_PARAMETERS`.
+    environment: usize,
+    /// The offset of `EnvironmentSize` in `RTL_USER_PROCESS_PARAMETERS`.
+    environment_size: usize,
+    /// The offset of `StartingX`, the first of the `STARTUPINFO` fields in
+    /// `RTL_USER_PROCESS_PARAMETERS`.
+    startup_info: usize,
+    /// The offset of `WindowTitle` in `RTL_USER_PROCESS_PARAMETERS`, followed by `DesktopInfo`.
+    window_title: usize,
+}
+
+/// The layout of a 32-bit PEB, either native or WOW64.
+const PEB32: PebLayout = PebLayout {
+    pointer_size: 4,
+    process_parameters: 0x10,
+    process_heap: 0x18,
+    number_of_heaps: 0x88,
+    process_heaps: 0x90,
+    current_directory: 0x24,
+    image_path_name: 0x38,
+    command_line: 0x40,
+    environment: 0x48,
+    environment_size: 0x290,
+    startup_info: 0x4c,
+    window_title: 0x70,
+};
+
+#[cfg(target_pointer_width = "64")]
+const PEB64: PebLayout = PebLayout {
+    pointer_size: 8,
+    process_parameters: 0x20,
+    process_heap: 0x30,
+    number_of_heaps: 0xe8,
+    process_heaps: 0xf0,
+    current_directory: 0x38,
+    image_path_name: 0x60,
+    command_line: 0x70,
+    environment: 0x80,
+    environment_size: 0x3f0,
+    startup_info: 0x88,
+    window_title: 0xb0,
+};
+
+impl Process {
+    /// Returns the address of the process environment block of the process.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` access right.
+    pub(crate) fn peb_address(&self) -> WinResult<usize> {
+        let mut info: ntdll::PROCESS_BASIC_INFORMATION = unsafe { mem::zeroed() };
+        self.query_information(ntdll::PROCESS_BASIC_INFORMATION_CLASS, &mut info)?;
+        Ok(info.PebBaseAddress as usize)
+    }
+
+    /// Returns the modules of the process in the order they were loaded, as listed by the loader.
+    ///
+    /// Modules loaded unusually late, after most of the process's dependencies, may have been
+    /// injected. Only the loader data matching the bitness of the current process is read, so the
+    /// 32-bit modules of a WOW64 process aren't listed by a 64-bit process.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn modules_ordered(&self) -> WinResult<Vec<OrderedModule>> {
+        let peb: ntdll::PEB = self.read_value(self.peb_address()?)?;
+        let ldr = peb.Ldr as usize;
+        if ldr == 0 {
+            // The loader hasn't been initialized yet.
+            return Ok(Vec::new());
+        }
+
+        let head = ldr + offset_of_load_order_list();
+        let ldr: ntdll::PEB_LDR_DATA = self.read_value(ldr)?;
+        let mut link = ldr.InLoadOrderModuleList.Flink as usize;
+        let mut modules = Vec::new();
+        while link != head && link != 0 {
+            if modules.len() >= MAX_LOADER_ENTRIES {
+                return Err(Error::Os(io::Error::from_raw_os_error(
+                    ERROR_BAD_LENGTH as _,
+                )));
+            }
+
+            // `InLoadOrderLinks` is the first field, so the link is the address of the entry.
+            let entry: ntdll::LDR_DATA_TABLE_ENTRY = self.read_value(link)?;
+            let base = entry.DllBase as usize;
+            modules.push(OrderedModule {
+                base,
+                size: entry.SizeOfImage as usize,
+                path: self.read_unicode_string(&entry.FullDllName)?.into(),
+                time_date_stamp: RemoteImage::new(self, base)
+                    .ok()
+                    .map(|image| image.time_date_stamp()),
+            });
+            link = entry.InLoadOrderLinks.Flink as usize;
+        }
+        Ok(modules)
+    }
+
+    /// Returns the heaps of the process, as listed in its process environment block.
+    ///
+    /// For a WOW64 process, the heaps of its 32-bit environment block are listed.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn heaps(&self) -> WinResult<Vec<HeapInfo>> {
+        let (peb, layout) = self.peb_layout()?;
+        let default = self.read_pointer(peb + layout.process_heap, layout.pointer_size)?;
+        let count: u32 = self.read_value(peb + layout.number_of_heaps)?;
+        if count > MAX_HEAPS {
+            return Err(Error::Os(io::Error::from_raw_os_error(
+                ERROR_BAD_LENGTH as _,
+            )));
+        }
+        let array = self.read_pointer(peb + layout.process_heaps, layout.pointer_size)?;
+        (0..count as usize)
+            .map(|i| {
+                let address =
+                    self.read_pointer(array + i * layout.pointer_size, layout.pointer_size)?;
+                Ok(HeapInfo {
+                    address,
+                    is_default: address == default,
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the address and layout of the environment block of the process. For a WOW64
+    /// process, that's its 32-bit environment block.
+    #[cfg(target_pointer_width = "64")]
+    fn peb_layout(&self) -> WinResult<(usize, &'static PebLayout)> {
+        let mut wow64_peb: usize = 0;
+        self.query_information(ntdll::PROCESS_WOW64_INFORMATION, &mut wow64_peb)?;
+        if wow64_peb != 0 {
+            Ok((wow64_peb, &PEB32))
+        } else {
+            Ok((self.peb_address()?, &PEB64))
+        }
+    }
+
+    /// Returns the address and layout of the environment block of the process.
+    ///
+    /// A 32-bit process running under WOW64 can't follow the 64-bit environment block of a
+    /// native process, so that fails with `ERROR_NOT_SUPPORTED`.
+    #[cfg(target_pointer_width = "32")]
+    fn peb_layout(&self) -> WinResult<(usize, &'static PebLayout)> {
+        if Process::current().is_wow64()? && !self.is_wow64()? {
+            return Err(Error::Os(io::Error::from_raw_os_error(
+                winapi::shared::winerror::ERROR_NOT_SUPPORTED as _,
+            )));
+        }
+        Ok((self.peb_address()?, &PEB32))
+    }
+
+    /// Returns the address of the `RTL_USER_PROCESS_PARAMETERS` of the process, and the layout
+    /// of its environment block.
+    fn process_parameters(&self) -> WinResult<(usize, &'static PebLayout)> {
+        let (peb, layout) = self.peb_layout()?;
+        let parameters = self.read_pointer(peb + layout.process_parameters, layout.pointer_size)?;
+        Ok((parameters, layout))
+    }
+
+    /// Reads the main process parameters of the process: its image path, command line, current
+    /// directory, environment and window title, with every pointer followed.
+    ///
+    /// For a WOW64 process, the parameters of its 32-bit environment block are read. From a
+    /// 32-bit process, a 64-bit target can't be read and this fails with `ERROR_NOT_SUPPORTED`.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn read_peb_parameters(&self) -> WinResult<ProcessParameters> {
+        let (parameters, layout) = self.process_parameters()?;
+        let environment = self.read_environment(parameters, layout)?;
+        Ok(ProcessParameters {
+            image_path: self
+                .read_unicode_string_at(parameters + layout.image_path_name, layout.pointer_size)?
+                .into(),
+            command_line: self
+                .read_unicode_string_at(parameters + layout.command_line, layout.pointer_size)?
+                .to_string_lossy()
+                .into_owned(),
+            current_directory: self
+                .read_unicode_string_at(parameters + layout.current_directory, layout.pointer_size)?
+                .into(),
+            environment,
+            window_title: self
+                .read_unicode_string_at(parameters + layout.window_title, layout.pointer_size)?
+                .to_string_lossy()
+                .into_owned(),
+        })
+    }
+
+    /// Returns the arguments of the process, as read from the command line in its process
+    /// parameters and split with `CommandLineToArgvW`. The first argument is usually the
+    /// executable.
+    ///
+    /// For a WOW64 process, the parameters of its 32-bit environment block are read.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn command_line(&self) -> WinResult<Vec<String>> {
+        let (parameters, layout) = self.process_parameters()?;
+        let command_line =
+            self.read_unicode_string_at(parameters + layout.command_line, layout.pointer_size)?;
+        split_command_line(&command_line)
+    }
+
+    /// Returns the environment variables of the process, as read from its process parameters.
+    ///
+    /// Names starting with `=`, such as the hidden per-drive current directories like `=C:`, are
+    /// kept whole. Names are case-sensitive keys, although Windows compares them ignoring case.
+    ///
+    /// For a WOW64 process, the parameters of its 32-bit environment block are read.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn environment(&self) -> WinResult<HashMap<String, String>> {
+        let (parameters, layout) = self.process_parameters()?;
+        Ok(self
+            .read_environment(parameters, layout)?
+            .into_iter()
+            .collect())
+    }
+
+    /// Reads the environment block pointed to by the process parameters, as name and value pairs.
+    fn read_environment(
+        &self,
+        parameters: usize,
+        layout: &PebLayout,
+    ) -> WinResult<Vec<(String, String)>> {
+        let environment =
+            self.read_pointer(parameters + layout.environment, layout.pointer_size)?;
+        if environment == 0 {
+            return Ok(Vec::new());
+        }
+        let size = self.read_pointer(parameters + layout.environment_size, layout.pointer_size)?;
+        Ok(self
+            .read_multi_wide_string(environment, size.min(MAX_ENVIRONMENT_SIZE))?
+            .into_iter()
+            .map(|variable| {
+                // Names may start with `=`, like the hidden per-drive directories `=C:`.
+                match variable.char_indices().skip(1).find(|&(_, c)| c == '=') {
+                    Some((pos, _)) => {
+                        (variable[..pos].to_string(), variable[pos + 1..].to_string())
+                    }
+                    None => (variable, String::new()),
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the current directory of the process, as read from its process parameters.
+    ///
+    /// For a WOW64 process, the parameters of its 32-bit environment block are read.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn current_directory(&self) -> WinResult<PathBuf> {
+        let (parameters, layout) = self.process_parameters()?;
+        Ok(self
+            .read_unicode_string_at(parameters + layout.current_directory, layout.pointer_size)?
+            .into())
+    }
+
+    /// Returns how the process was asked to show its first window, as read from its process
+    /// parameters. These are the `STARTUPINFO` values passed to `CreateProcess`.
+    ///
+    /// For a WOW64 process, the parameters of its 32-bit environment block are read.
+    ///
+    /// The handle must have the `PROCESS_QUERY_LIMITED_INFORMATION` and `PROCESS_VM_READ` access
+    /// rights.
+    pub fn startup_info(&self) -> WinResult<StartupInfo> {
+        let (parameters, layout) = self.process_parameters()?;
+        let fields: StartupFields = self.read_value(parameters + layout.startup_info)?;
+        let title = parameters + layout.window_title;
+        // A `UNICODE_STRING` is two pointers wide.
+        let desktop = title + 2 * layout.pointer_size;
+        Ok(StartupInfo {
+            x: fields.starting_x,
+            y: fields.starting_y,
+            width: fields.count_x,
+            height: fields.count_y,
+            console_columns: fields.count_chars_x,
+            console_rows: fields.count_chars_y,
+            fill_attribute: fields.fill_attribute,
+            flags: fields.window_flags,
+            show_window: fields.show_window_flags as u16,
+            title: self
+                .read_unicode_string_at(title, layout.pointer_size)?
+                .to_string_lossy()
+                .into_owned(),
+            desktop: self
+                .read_unicode_string_at(desktop, layout.pointer_size)?
+                .to_string_lossy()
+                .into_owned(),
+        })
+    }
+
+    /// Reads a `UNICODE_STRING` whose pointers have the specified size, and its contents.
+    fn read_unicode_string_at(&self, address: usize, pointer_size: usize) -> WinResult<OsString> {
+        let length: u16 = self.read_value(address)?;
+        // `Buffer` follows the two lengths, aligned to the pointer size.
+        let buffer = self.read_pointer(address + pointer_size, pointer_size)?;
+        self.read_wide(buffer, length as usize)
+    }
+
+    /// Reads a pointer of the specified size, which may differ from the native one.
+    fn read_pointer(&self, address: usize, size: usize) -> WinResult<usize> {
+        if size == 4 {
+            self.read_value::<u32>(address).map(|p| p as usize)
+        } else {
+            self.read_value::<u64>(address).map(|p| p as usize)
+        }
+    }
+
+    /// Reads the contents of a `UNICODE_STRING` of the process.
+    fn read_unicode_string(&self, string: &UNICODE_STRING) -> WinResult<OsString> {
+        self.read_wide(string.Buffer as usize, string.Length as usize)
+    }
+
+    /// Reads `length` bytes of UTF-16 text.
+    fn read_wide(&self, address: usize, length: usize) -> WinResult<OsString> {
+        let mut buffer = vec![0u16; length / 2];
+        unsafe {
+            let bytes = slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u8, buffer.len() * 2);
+            self.read_exact(address, bytes)?;
+        }
+        Ok(OsString::from_wide(&buffer))
+    }
+}
+
+/// Splits a command line into arguments with the rules of `CommandLineToArgvW`.
+fn split_command_line(command_line: &OsStr) -> WinResult<Vec<String>> {
+    // An empty command line would be split as the path of the current executable.
+    if command_line.is_empty() {
+        return Ok(Vec::new());
+    }
+    let command_line = WideCString::from_os_str(command_line).map_err(|e| Error::NulErrorW {
+        pos: e.nul_position(),
+        data: e.into_vec(),
+    })?;
+    unsafe {
+        let mut count = 0;
+        let argv = CommandLineToArgvW(command_line.as_ptr(), &mut count);
+        if argv.is_null() {
+            return Err(Error::last_os_error());
+        }
+        let args = slice::from_raw_parts(argv, count as usize)
+            .iter()
+            .map(|&arg| WideCStr::from_ptr_str(arg).to_string_lossy())
+            .collect();
+        LocalFree(argv as _);
+        Ok(args)
+    }
+}
+
+/// Returns the offset of `InLoadOrderModuleList` in `PEB_LDR_DATA`.
+fn offset_of_load_order_list() -> usize {
+    let ldr: ntdll::PEB_LDR_DATA = unsafe { mem::zeroed() };
+    &ldr.InLoadOrderModuleList as *const _ as usize - &ldr as *const _ as usize
+}
+
+/// A heap of a process, as returned by `Process::heaps`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct HeapInfo {
+    /// The base address of the heap, which is also its handle in the owning process.
+    pub address: usize,
+    /// Whether the heap is the default heap of the process, returned by `GetProcessHeap`.
+    pub is_default: bool,
+}
+
+/// How a process was asked to show its first window, as returned by `Process::startup_info`.
+///
+/// Maps to the fields of a Windows [STARTUPINFO][STARTUPINFO]. Each field is only meaningful if
+/// the matching `STARTF_*` flag is set in `flags`.
+///
+/// [STARTUPINFO]: https://docs.microsoft.com/en-us/windows/desktop/api/processthreadsapi/ns-processthreadsapi-startupinfow
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct StartupInfo {
+    /// The horizontal position of the window, if `STARTF_USEPOSITION` is set.
+    pub x: u32,
+    /// The vertical position of the window, if `STARTF_USEPOSITION` is set.
+    pub y: u32,
+    /// The width of the window, if `STARTF_USESIZE` is set.
+    pub width: u32,
+    /// The height of the window, if `STARTF_USESIZE` is set.
+    pub height: u32,
+    /// The console screen buffer width, in characters, if `STARTF_USECOUNTCHARS` is set.
+    pub console_columns: u32,
+    /// The console screen buffer height, in characters, if `STARTF_USECOUNTCHARS` is set.
+    pub console_rows: u32,
+    /// The initial console text and background colors, if `STARTF_USEFILLATTRIBUTE` is set.
+    pub fill_attribute: u32,
+    /// The `STARTF_*` flags telling which fields were set by the creator of the process.
+    pub flags: u32,
+    /// The `SW_*` show command of the first window, if `STARTF_USESHOWWINDOW` is set.
+    pub show_window: u16,
+    /// The title of the console window, or the path of the executable or shortcut.
+    pub title: String,
+    /// The desktop and window station of the process, if set.
+    pub desktop: String,
+}
+
+/// The main process parameters of a process, as returned by `Process::read_peb_parameters`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ProcessParameters {
+    /// The path of the executable of the process.
+    pub image_path: PathBuf,
+    /// The command line of the process, including the executable.
+    pub command_line: String,
+    /// The current directory of the process.
+    pub current_directory: PathBuf,
+    /// The environment variables of the process, as name and value pairs, in the order they're
+    /// stored.
+    pub environment: Vec<(String, String)>,
+    /// The title of the console window, or the path of the executable or shortcut.
+    pub window_title: String,
+}
@@ -1,45 +1,99 @@
-use crate::{Error, Handle, Process, WinResult};
+use super::{filetime_to_duration, filetime_to_system_time, timeout_millis};
+use crate::{
+    dynamic,
+    ntdll,
+    Access,
+    CpuSet,
+    Error,
+    GroupAffinity,
+    Handle,
+    Process,
+    ProcessorNumber,
+    WinResult,
+};
+use bitflags::bitflags;
 use std::{
+    collections::HashMap,
+    io,
     mem,
     ops::Deref,
     os::windows::io::{AsRawHandle, FromRawHandle, IntoRawHandle, RawHandle},
+    ptr::null_mut,
+    slice,
+    time::{Duration, SystemTime},
 };
+use widestring::WideCString;
 use winapi::{
+    ctypes::c_void,
     shared::{
         basetsd::{DWORD_PTR, ULONG64},
-        minwindef::DWORD,
+        minwindef::{DWORD, FILETIME},
+        ntstatus::STATUS_PENDING,
+        winerror::{ERROR_NOT_SUPPORTED, ERROR_PROC_NOT_FOUND, WAIT_TIMEOUT},
     },
     um::{
+        handleapi::INVALID_HANDLE_VALUE,
+        minwinbase::STILL_ACTIVE,
         processthreadsapi::{
             GetCurrentThread,
+            GetCurrentThreadId,
+            GetExitCodeThread,
+            GetThreadContext,
             GetThreadId,
             GetThreadIdealProcessorEx,
             GetThreadPriority,
+            GetThreadTimes,
             OpenThread,
             ResumeThread,
+            SetThreadContext,
             SetThreadIdealProcessor,
+            SetThreadIdealProcessorEx,
             SetThreadPriority,
             SuspendThread,
             TerminateThread,
         },
+        processtopologyapi::{GetThreadGroupAffinity, SetThreadGroupAffinity},
         realtimeapiset::QueryThreadCycleTime,
-        tlhelp32::{Thread32Next, THREADENTRY32},
+        synchapi::WaitForSingleObject,
+        tlhelp32::{
+            CreateToolhelp32Snapshot,
+            Thread32First,
+            Thread32Next,
+            TH32CS_SNAPTHREAD,
+            THREADENTRY32,
+        },
         winbase::{
+            LocalFree,
             SetThreadAffinityMask,
+            INFINITE,
             THREAD_MODE_BACKGROUND_BEGIN,
             THREAD_MODE_BACKGROUND_END,
             THREAD_PRIORITY_ABOVE_NORMAL,
             THREAD_PRIORITY_BELOW_NORMAL,
+            THREAD_PRIORITY_ERROR_RETURN,
             THREAD_PRIORITY_HIGHEST,
             THREAD_PRIORITY_IDLE,
             THREAD_PRIORITY_LOWEST,
             THREAD_PRIORITY_NORMAL,
             THREAD_PRIORITY_TIME_CRITICAL,
+            WAIT_FAILED,
         },
-        winnt::{self, PROCESSOR_NUMBER, THREAD_ALL_ACCESS},
+        winnt::{self, CONTEXT, GROUP_AFFINITY, PROCESSOR_NUMBER},
     },
 };
 
+/// The offset of `SameTebFlags` in a 64-bit TEB.
+const TEB64_SAME_TEB_FLAGS: usize = 0x17ee;
+/// The offset of `SameTebFlags` in a 32-bit TEB.
+const TEB32_SAME_TEB_FLAGS: usize = 0xfca;
+/// The offset of the 32-bit TEB of a WOW64 thread from its 64-bit TEB.
+const TEB32_OFFSET_IN_WOW64: usize = 0x2000;
+/// The `HasFiberData` bit of `SameTebFlags`.
+const TEB_HAS_FIBER_DATA: u16 = 0x4;
+
+#[repr(C, align(16))]
+struct AlignedContext(CONTEXT);
+
 /// A handle to a running thread.
 #[derive(Debug)]
 pub struct Thread {
@@ -47,10 +101,18 @@ pub struct Thread {
 }
 
 impl Thread {
-    /// Creates a thread handle from a thread ID.
+    /// Creates a thread handle from a thread ID. Requests all access.
     pub fn from_id(id: u32) -> WinResult<Thread> {
+        Thread::from_id_with_access(id, ThreadAccess::THREAD_ALL_ACCESS)
+    }
+
+    /// Creates a thread handle from a thread ID, with the specified access rights.
+    ///
+    /// Threads of protected or system processes usually can't be opened with all access, but
+    /// still allow `THREAD_QUERY_LIMITED_INFORMATION`.
+    pub fn from_id_with_access(id: u32, access: ThreadAccess) -> WinResult<Thread> {
         unsafe {
-            let handle = OpenThread(THREAD_ALL_ACCESS, 0, id);
+            let handle = OpenThread(access.bits, 0, id);
             if handle.is_null() {
                 Err(Error::last_os_error())
             } else {
@@ -62,6 +124,9 @@ impl Thread {
     }
 
     /// Returns a handle to the current thread.
+    ///
+    /// This is a pseudo-handle, which always refers to the thread using it. Use `from_id` with
+    /// `current_id` for a handle to this thread that can be used from other threads.
     pub fn current() -> Thread {
         unsafe {
             Thread {
@@ -70,15 +135,317 @@ impl Thread {
         }
     }
 
+    /// Returns the id of the current thread.
+    pub fn current_id() -> u32 {
+        unsafe { GetCurrentThreadId() }
+    }
+
     pub fn handle(&self) -> &Handle {
         &self.handle
     }
 
+    /// Sets whether the thread handle is inherited by child processes.
+    pub fn set_inheritable(&self, inheritable: bool) -> WinResult {
+        self.handle.set_inheritable(inheritable)
+    }
+
+    /// Sets whether the thread handle is protected from being closed.
+    ///
+    /// Dropping a thread whose handle is protected panics, so the protection must be removed
+    /// beforehand.
+    pub fn set_protected_from_close(&self, protected: bool) -> WinResult {
+        self.handle.set_protected_from_close(protected)
+    }
+
     /// Return's the thread's ID.
     pub fn id(&self) -> u32 {
         unsafe { GetThreadId(self.handle.as_raw_handle() as winnt::HANDLE) }
     }
 
+    /// Returns true if the thread has not yet exited.
+    ///
+    /// A thread that exited with the code `STILL_ACTIVE` (259) is told apart from a running one by
+    /// checking whether its handle is signaled.
+    ///
+    /// The handle must have the `THREAD_QUERY_LIMITED_INFORMATION` and `SYNCHRONIZE` access rights.
+    pub fn is_alive(&self) -> bool {
+        unsafe {
+            let handle = self.handle.as_raw_handle() as winnt::HANDLE;
+            let mut status = 0;
+            if GetExitCodeThread(handle, &mut status) == 0 || status != STILL_ACTIVE {
+                false
+            } else {
+                WaitForSingleObject(handle, 0) == WAIT_TIMEOUT
+            }
+        }
+    }
+
+    /// Returns the exit code of the thread, or `None` if it's still running.
+    ///
+    /// A thread that exited with the code `STILL_ACTIVE` (259) also returns `None`. Use
+    /// `wait_timeout` with a zero timeout to tell it apart from a running one.
+    ///
+    /// The handle must have the `THREAD_QUERY_INFORMATION` or `THREAD_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn exit_code(&self) -> WinResult<Option<u32>> {
+        match self.exit_status()? {
+            STILL_ACTIVE => Ok(None),
+            code => Ok(Some(code)),
+        }
+    }
+
+    /// Waits until the thread exits, and returns its exit code.
+    ///
+    /// For a remote thread started at a function like `LoadLibraryW`, the exit code is the
+    /// function's return value. Exit codes are 32-bit, so a returned pointer such as the `HMODULE`
+    /// of the loaded module is truncated in a 64-bit process, and only tells success from failure.
+    ///
+    /// The handle must have the `SYNCHRONIZE` and `THREAD_QUERY_LIMITED_INFORMATION` access
+    /// rights.
+    pub fn wait(&self) -> WinResult<u32> {
+        self.wait_millis(INFINITE)?;
+        self.exit_status()
+    }
+
+    /// Waits until the thread exits or the timeout elapses. Returns the exit code of the thread,
+    /// or `None` if it's still running.
+    ///
+    /// A zero timeout checks whether the thread has exited without blocking.
+    ///
+    /// The handle must have the `SYNCHRONIZE` and `THREAD_QUERY_LIMITED_INFORMATION` access
+    /// rights.
+    pub fn wait_timeout(&self, timeout: Duration) -> WinResult<Option<u32>> {
+        if self.wait_millis(timeout_millis(timeout))? {
+            self.exit_status().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Waits on the thread handle. Returns false if the timeout elapsed first.
+    fn wait_millis(&self, millis: DWORD) -> WinResult<bool> {
+        match unsafe { WaitForSingleObject(self.handle.as_raw_handle() as winnt::HANDLE, millis) } {
+            WAIT_FAILED => Err(Error::last_os_error()),
+            WAIT_TIMEOUT => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    /// Returns the value of `GetExitCodeThread`, which is `STILL_ACTIVE` while the thread runs.
+    fn exit_status(&self) -> WinResult<u32> {
+        unsafe {
+            let mut status = 0;
+            let ret = GetExitCodeThread(self.handle.as_raw_handle() as winnt::HANDLE, &mut status);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(status)
+            }
+        }
+    }
+
+    /// Returns the scheduling state of the thread.
+    ///
+    /// This tells apart a thread that has exited but whose handle is still open, a thread that is
+    /// frozen by a suspension, and one that is blocked or runnable.
+    ///
+    /// The handle must have the `THREAD_QUERY_LIMITED_INFORMATION` access right.
+    pub fn state(&self) -> WinResult<ThreadState> {
+        let info = self.basic_information()?;
+        if info.ExitStatus != STATUS_PENDING {
+            return Ok(ThreadState::Terminated);
+        }
+
+        let id = info.ClientId.UniqueThread as usize;
+        let snapshot = ntdll::SystemProcesses::snapshot()?;
+        let thread = snapshot
+            .iter()
+            .flat_map(|(_, threads)| threads)
+            .find(|t| t.ClientId.UniqueThread as usize == id);
+        Ok(match thread {
+            None => ThreadState::Terminated,
+            Some(t) => match t.ThreadState {
+                ntdll::THREAD_STATE_TERMINATED => ThreadState::Terminated,
+                ntdll::THREAD_STATE_WAITING if t.WaitReason == ntdll::WAIT_REASON_SUSPENDED => {
+                    ThreadState::Suspended
+                }
+                ntdll::THREAD_STATE_WAITING => ThreadState::Waiting,
+                _ => ThreadState::Running,
+            },
+        })
+    }
+
+    /// Returns the base address of the `GS` segment of the thread, which points to its thread
+    /// environment block (TEB) and thread-local storage slots.
+    ///
+    /// The `CONTEXT` of a thread only holds segment selectors, not their bases, so the address is
+    /// read from the thread's basic information instead, and the thread doesn't need to be
+    /// suspended. Segment bases can't be changed from user mode.
+    ///
+    /// For a WOW64 thread, this is the address of the 64-bit TEB.
+    ///
+    /// The handle must have the `THREAD_QUERY_LIMITED_INFORMATION` access right.
+    #[cfg(target_arch = "x86_64")]
+    pub fn gs_base(&self) -> WinResult<usize> {
+        Ok(self.basic_information()?.TebBaseAddress as usize)
+    }
+
+    /// Returns the base address of the `FS` segment of the thread, which points to its thread
+    /// environment block (TEB) and thread-local storage slots.
+    ///
+    /// The `CONTEXT` of a thread only holds segment selectors, not their bases, so the address is
+    /// read from the thread's basic information instead, and the thread doesn't need to be
+    /// suspended. Segment bases can't be changed from user mode.
+    ///
+    /// The handle must have the `THREAD_QUERY_LIMITED_INFORMATION` access right.
+    #[cfg(target_arch = "x86")]
+    pub fn fs_base(&self) -> WinResult<usize> {
+        Ok(self.basic_information()?.TebBaseAddress as usize)
+    }
+
+    /// Returns true if the thread has been converted to a fiber, and so may be running fibers
+    /// instead of its original stack.
+    ///
+    /// This reads the `HasFiberData` flag of the thread's environment block (TEB), set by
+    /// `ConvertThreadToFiber` and cleared by `ConvertFiberToThread`. The OS keeps no list of the
+    /// fibers of a process, so they can't be enumerated, and which fiber the thread is running
+    /// can't be told from the flag either. For a WOW64 thread, the 32-bit TEB is read. A 32-bit
+    /// process can't inspect the threads of a 64-bit process, and fails with
+    /// `ERROR_NOT_SUPPORTED`.
+    ///
+    /// The thread handle must have the `THREAD_QUERY_LIMITED_INFORMATION` access right. The
+    /// process that owns the thread is opened with the `PROCESS_QUERY_LIMITED_INFORMATION` and
+    /// `PROCESS_VM_READ` access rights.
+    pub fn is_fiber(&self) -> WinResult<bool> {
+        let process_id = self.basic_information()?.ClientId.UniqueProcess as u32;
+        let process = Process::from_id_with_access(
+            process_id,
+            Access::PROCESS_QUERY_LIMITED_INFORMATION | Access::PROCESS_VM_READ,
+        )?;
+        self.has_fiber_data(&process)
+    }
+
+    /// Reads the `HasFiberData` flag from the TEB of the thread, which is owned by `process`.
+    pub(crate) fn has_fiber_data(&self, process: &Process) -> WinResult<bool> {
+        let teb = self.basic_information()?.TebBaseAddress as usize;
+        let flags_address = if cfg!(target_pointer_width = "64") {
+            let mut wow64_peb: usize = 0;
+            process.query_information(ntdll::PROCESS_WOW64_INFORMATION, &mut wow64_peb)?;
+            if wow64_peb != 0 {
+                teb + TEB32_OFFSET_IN_WOW64 + TEB32_SAME_TEB_FLAGS
+            } else {
+                teb + TEB64_SAME_TEB_FLAGS
+            }
+        } else {
+            // The TEB of a native 64-bit thread doesn't have the 32-bit layout.
+            if Process::current().is_wow64()? && !process.is_wow64()? {
+                return Err(Error::Os(io::Error::from_raw_os_error(
+                    ERROR_NOT_SUPPORTED as _,
+                )));
+            }
+            teb + TEB32_SAME_TEB_FLAGS
+        };
+        let flags: u16 = process.read_value(flags_address)?;
+        Ok(flags & TEB_HAS_FIBER_DATA != 0)
+    }
+
+    /// Returns the parts of the thread's `CONTEXT` selected by `flags`. Other fields are zeroed.
+    ///
+    /// Requesting only what's needed is much cheaper than copying the whole context, which
+    /// includes the floating point state. The thread should be suspended, or the values may be
+    /// stale by the time they're returned.
+    ///
+    /// The handle must have the `THREAD_GET_CONTEXT` access right.
+    pub fn context(&self, flags: ContextFlags) -> WinResult<CONTEXT> {
+        unsafe {
+            // `GetThreadContext` requires a 16-byte aligned `CONTEXT` on x86_64, which the
+            // binding doesn't guarantee.
+            let mut context: AlignedContext = mem::zeroed();
+            context.0.ContextFlags = flags.bits();
+            let ret =
+                GetThreadContext(self.handle.as_raw_handle() as winnt::HANDLE, &mut context.0);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(context.0)
+            }
+        }
+    }
+
+    /// Sets the parts of the thread's `CONTEXT` selected by its `ContextFlags`.
+    ///
+    /// The thread should be suspended, or the registers it's using may be overwritten mid-flight.
+    ///
+    /// The handle must have the `THREAD_SET_CONTEXT` access right.
+    pub fn set_context(&mut self, context: &CONTEXT) -> WinResult {
+        unsafe {
+            // `SetThreadContext` has the same alignment requirement as `GetThreadContext`.
+            let context = AlignedContext(*context);
+            let ret = SetThreadContext(self.handle.as_raw_handle() as winnt::HANDLE, &context.0);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Suspends the thread, passes the parts of its `CONTEXT` selected by `flags` to the closure,
+    /// writes the possibly modified context back, and resumes the thread.
+    ///
+    /// The thread is resumed even if reading or writing the context fails, or the closure panics.
+    /// It must not be the current thread, which can't suspend itself and carry on.
+    ///
+    /// The handle must have the `THREAD_SUSPEND_RESUME`, `THREAD_GET_CONTEXT` and
+    /// `THREAD_SET_CONTEXT` access rights.
+    pub fn with_suspended<F, R>(&mut self, flags: ContextFlags, f: F) -> WinResult<R>
+    where
+        F: FnOnce(&mut CONTEXT) -> R,
+    {
+        self.suspend()?;
+        let guard = ResumeOnDrop(self.handle.as_raw_handle() as winnt::HANDLE);
+        let ret = self.context(flags).and_then(|mut context| {
+            let ret = f(&mut context);
+            self.set_context(&context).map(|_| ret)
+        });
+        // Resume here instead, to report failure.
+        mem::forget(guard);
+        self.resume()?;
+        ret
+    }
+
+    /// Queries an information class of the thread with `NtQueryInformationThread`, for classes
+    /// this crate doesn't wrap. On success, returns the number of bytes written to the buffer.
+    ///
+    /// The layout of the buffer and the required access rights depend on the class. Some classes
+    /// also read input from the buffer.
+    pub fn query_information_raw(&self, class: u32, buf: &mut [u8]) -> WinResult<usize> {
+        let mut len = 0;
+        ntdll::nt_result(unsafe {
+            ntdll::NtQueryInformationThread(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                class,
+                buf.as_mut_ptr() as _,
+                buf.len() as _,
+                &mut len,
+            )
+        })?;
+        Ok(len as usize)
+    }
+
+    /// Queries an information class whose data has a fixed size into `value`.
+    fn query_information<T: Copy>(&self, class: u32, value: &mut T) -> WinResult {
+        let buf =
+            unsafe { slice::from_raw_parts_mut(value as *mut T as *mut u8, mem::size_of::<T>()) };
+        self.query_information_raw(class, buf).map(|_| ())
+    }
+
+    fn basic_information(&self) -> WinResult<ntdll::THREAD_BASIC_INFORMATION> {
+        let mut info: ntdll::THREAD_BASIC_INFORMATION = unsafe { mem::zeroed() };
+        self.query_information(ntdll::THREAD_BASIC_INFORMATION_CLASS, &mut info)?;
+        Ok(info)
+    }
+
     /// Returns the thread's cycle time.
     pub fn cycle_time(&self) -> WinResult<u64> {
         unsafe {
@@ -93,18 +460,114 @@ impl Thread {
         }
     }
 
+    /// Returns the creation and exit times of the thread, and the CPU time it has spent.
+    ///
+    /// The handle must have the `THREAD_QUERY_INFORMATION` or `THREAD_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn times(&self) -> WinResult<ThreadTimes> {
+        unsafe {
+            let mut times: [FILETIME; 4] = mem::zeroed();
+            let [creation, exit, kernel, user] = &mut times;
+            let ret = GetThreadTimes(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                creation,
+                exit,
+                kernel,
+                user,
+            );
+            if ret == 0 {
+                return Err(Error::last_os_error());
+            }
+            let [creation, exit, kernel, user] = times;
+            let raw =
+                |time: FILETIME| (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64;
+            Ok(ThreadTimes {
+                creation: filetime_to_system_time(raw(creation)),
+                // The exit time is left zeroed while the thread runs.
+                exit: match raw(exit) {
+                    0 => None,
+                    exit => Some(filetime_to_system_time(exit)),
+                },
+                kernel: filetime_to_duration(raw(kernel)),
+                user: filetime_to_duration(raw(user)),
+            })
+        }
+    }
+
+    /// Returns the description of the thread, which debuggers and crash dumps show as its name.
+    /// Threads are created without a description, in which case it's empty.
+    ///
+    /// Requires Windows 10 version 1607 or later, and fails with `ERROR_PROC_NOT_FOUND` before.
+    /// The handle must have the `THREAD_QUERY_LIMITED_INFORMATION` access right.
+    pub fn description(&self) -> WinResult<String> {
+        type GetThreadDescriptionFn =
+            unsafe extern "system" fn(winnt::HANDLE, *mut *mut u16) -> i32;
+
+        let function = description_function("GetThreadDescription")?;
+        unsafe {
+            let function = mem::transmute::<*mut c_void, GetThreadDescriptionFn>(function);
+            let mut description = null_mut();
+            let ret = function(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                &mut description,
+            );
+            if ret < 0 {
+                return Err(Error::Os(io::Error::from_raw_os_error(ret)));
+            }
+            if description.is_null() {
+                return Ok(String::new());
+            }
+            let len = (0..).take_while(|&i| *description.offset(i) != 0).count();
+            let ret = String::from_utf16_lossy(slice::from_raw_parts(description, len));
+            LocalFree(description as _);
+            Ok(ret)
+        }
+    }
+
+    /// Sets the description of the thread, which debuggers and crash dumps show as its name.
+    ///
+    /// Requires Windows 10 version 1607 or later, and fails with `ERROR_PROC_NOT_FOUND` before.
+    /// The handle must have the `THREAD_SET_LIMITED_INFORMATION` access right.
+    pub fn set_description(&mut self, description: &str) -> WinResult {
+        type SetThreadDescriptionFn = unsafe extern "system" fn(winnt::HANDLE, *const u16) -> i32;
+
+        let function = description_function("SetThreadDescription")?;
+        let description = WideCString::from_str(description).map_err(|e| Error::NulErrorW {
+            pos: e.nul_position(),
+            data: e.into_vec(),
+        })?;
+        unsafe {
+            let function = mem::transmute::<*mut c_void, SetThreadDescriptionFn>(function);
+            let ret = function(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                description.as_ptr(),
+            );
+            if ret < 0 {
+                Err(Error::Os(io::Error::from_raw_os_error(ret)))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
     /// Returns the priority level of the thread.
     ///
+    /// `GetThreadPriority` reports failure with `THREAD_PRIORITY_ERROR_RETURN`, since zero is the
+    /// normal priority level.
+    ///
+    /// Threads of processes in the realtime priority class can also have levels between the
+    /// standard ones, which fail with `ERROR_NOT_SUPPORTED`.
+    ///
     /// The handle must have the `THREAD_QUERY_INFORMATION` or `THREAD_QUERY_LIMITED_INFORMATION`
     /// access right.
     pub fn priority(&self) -> WinResult<PriorityLevel> {
         unsafe {
             let ret = GetThreadPriority(self.handle.as_raw_handle() as winnt::HANDLE);
-            if ret == 0 {
-                Err(Error::last_os_error())
-            } else {
-                Ok(PriorityLevel::from_code(ret as _))
+            if ret as DWORD == THREAD_PRIORITY_ERROR_RETURN {
+                return Err(Error::last_os_error());
             }
+            PriorityLevel::from_code(ret as _)
+                .ok_or_else(|| Error::Os(io::Error::from_raw_os_error(ERROR_NOT_SUPPORTED as _)))
         }
     }
 
@@ -179,9 +642,11 @@ impl Thread {
     /// Suspends the thread.
     ///
     /// If the function succeeds, the return value is the thread's previous suspend count.
+    /// `SuspendThread` reports failure with `(DWORD)-1` rather than zero, which is never a valid
+    /// count.
     ///
     /// The handle must have the `THREAD_SUSPEND_RESUME` access right.
-    pub fn suspend(&mut self) -> WinResult<u32> {
+    pub fn suspend(&self) -> WinResult<u32> {
         unsafe {
             let ret = SuspendThread(self.handle.as_raw_handle() as winnt::HANDLE);
             if ret == u32::max_value() {
@@ -192,12 +657,23 @@ impl Thread {
         }
     }
 
+    /// Returns the thread's suspend count without changing it. The thread runs if it's zero.
+    ///
+    /// Requires Windows 8.1 or later. The handle must have the `THREAD_QUERY_LIMITED_INFORMATION`
+    /// access right.
+    pub fn suspend_count(&self) -> WinResult<u32> {
+        let mut count: u32 = 0;
+        self.query_information(ntdll::THREAD_SUSPEND_COUNT, &mut count)?;
+        Ok(count)
+    }
+
     /// Resumes the thread.
     ///
     /// If the function succeeds, the return value is the thread's previous suspend count.
+    /// Like `SuspendThread`, `ResumeThread` reports failure with `(DWORD)-1`.
     ///
     /// The handle must have the `THREAD_SUSPEND_RESUME` access right.
-    pub fn resume(&mut self) -> WinResult<u32> {
+    pub fn resume(&self) -> WinResult<u32> {
         unsafe {
             let ret = ResumeThread(self.handle.as_raw_handle() as winnt::HANDLE);
             if ret == u32::max_value() {
@@ -222,8 +698,34 @@ impl Thread {
         }
     }
 
-    /// Returns the thread's ideal processor.
+    /// Returns the thread's ideal processor, within its processor group.
+    ///
+    /// The group isn't returned, so on systems with more than 64 logical processors the number is
+    /// ambiguous. Prefer `ideal_processor_ex` there.
     pub fn ideal_processor(&self) -> WinResult<u32> {
+        Ok(self.ideal_processor_ex()?.number as u32)
+    }
+
+    /// Sets the thread's ideal processor. On success, returns the previous ideal processor.
+    ///
+    /// The processor is within the thread's current processor group, so on systems with more than
+    /// 64 logical processors, prefer `set_ideal_processor_ex`, which can target any group.
+    pub fn set_ideal_processor(&mut self, processor: u32) -> WinResult<u32> {
+        unsafe {
+            let ret = SetThreadIdealProcessor(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                processor as DWORD,
+            );
+            if ret == DWORD::max_value() {
+                Err(Error::last_os_error())
+            } else {
+                Ok(ret)
+            }
+        }
+    }
+
+    /// Returns the thread's ideal processor, including its processor group.
+    pub fn ideal_processor_ex(&self) -> WinResult<ProcessorNumber> {
         unsafe {
             let mut ideal: PROCESSOR_NUMBER = mem::zeroed();
             let ret =
@@ -231,27 +733,92 @@ impl Thread {
             if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(ideal.Number as u32)
+                Ok(ideal.into())
             }
         }
     }
 
-    /// Sets the thread's ideal processor. On success, returns the previous ideal processor.
-    pub fn set_ideal_processor(&mut self, processor: u32) -> WinResult<u32> {
+    /// Sets the thread's ideal processor, which may be in any processor group. On success,
+    /// returns the previous ideal processor.
+    ///
+    /// Unlike `set_ideal_processor`, this can target processors outside of group 0 on systems
+    /// with more than 64 logical processors.
+    pub fn set_ideal_processor_ex(
+        &mut self,
+        processor: ProcessorNumber,
+    ) -> WinResult<ProcessorNumber> {
         unsafe {
-            let ret = SetThreadIdealProcessor(
+            let mut ideal: PROCESSOR_NUMBER = processor.into();
+            let mut previous: PROCESSOR_NUMBER = mem::zeroed();
+            let ret = SetThreadIdealProcessorEx(
                 self.handle.as_raw_handle() as winnt::HANDLE,
-                processor as DWORD,
+                &mut ideal,
+                &mut previous,
             );
-            if ret == DWORD::max_value() {
+            if ret == 0 {
                 Err(Error::last_os_error())
             } else {
-                Ok(ret)
+                Ok(previous.into())
+            }
+        }
+    }
+
+    /// Returns the set of processors the thread is allowed to run on.
+    ///
+    /// This is the preferred alternative to `affinity_mask`.
+    pub fn affinity(&self) -> WinResult<CpuSet> {
+        Ok(CpuSet::from_mask(self.affinity_mask()? as u64))
+    }
+
+    /// Sets the set of processors the thread is allowed to run on. On success, returns the
+    /// previous set.
+    ///
+    /// This is the preferred alternative to `set_affinity_mask`. See it for details.
+    pub fn set_affinity_set(&mut self, set: &CpuSet) -> WinResult<CpuSet> {
+        Ok(CpuSet::from_mask(
+            self.set_affinity_mask(set.mask() as usize)? as u64,
+        ))
+    }
+
+    /// Returns the processor group of the thread and the processors it's allowed to run on in it.
+    pub fn group_affinity(&self) -> WinResult<GroupAffinity> {
+        unsafe {
+            let mut affinity: GROUP_AFFINITY = mem::zeroed();
+            let ret =
+                GetThreadGroupAffinity(self.handle.as_raw_handle() as winnt::HANDLE, &mut affinity);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(affinity.into())
+            }
+        }
+    }
+
+    /// Sets the processor group of the thread and the processors it's allowed to run on in it. On
+    /// success, returns the previous group affinity.
+    ///
+    /// Unlike `set_affinity_mask`, this can move the thread outside of its current group on
+    /// systems with more than 64 logical processors.
+    pub fn set_group_affinity(&mut self, affinity: GroupAffinity) -> WinResult<GroupAffinity> {
+        unsafe {
+            let affinity: GROUP_AFFINITY = affinity.into();
+            let mut previous: GROUP_AFFINITY = mem::zeroed();
+            let ret = SetThreadGroupAffinity(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                &affinity,
+                &mut previous,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(previous.into())
             }
         }
     }
 
     /// Returns the thread's current affinity mask.
+    ///
+    /// Prefer `affinity`, which returns a `CpuSet`.
     pub fn affinity_mask(&self) -> WinResult<usize> {
         unsafe {
             let affinity = SetThreadAffinityMask(
@@ -274,6 +841,8 @@ impl Thread {
 
     /// Sets the affinity mask of the thread. On success, returns the previous affinity mask.
     ///
+    /// Prefer `set_affinity_set`, which takes and returns a `CpuSet`.
+    ///
     /// A thread affinity mask is a bit vector in which each bit represents a logical processor
     /// that a thread is allowed to run on. A thread affinity mask must be a subset of the process
     /// affinity mask for the containing process of a thread. A thread can only run on the
@@ -314,6 +883,24 @@ impl Thread {
     }
 }
 
+/// Resumes a suspended thread when dropped, so that it isn't left suspended by a panic.
+struct ResumeOnDrop(winnt::HANDLE);
+
+impl Drop for ResumeOnDrop {
+    fn drop(&mut self) {
+        unsafe {
+            ResumeThread(self.0);
+        }
+    }
+}
+
+/// Looks up one of the thread description functions, which are missing before Windows 10 version
+/// 1607.
+fn description_function(name: &str) -> WinResult<*mut c_void> {
+    dynamic::proc_address("kernel32", name)
+        .ok_or_else(|| Error::Os(io::Error::from_raw_os_error(ERROR_PROC_NOT_FOUND as _)))
+}
+
 impl AsRawHandle for Thread {
     fn as_raw_handle(&self) -> RawHandle {
         self.handle.as_raw_handle()
@@ -342,60 +929,162 @@ impl IntoRawHandle for Thread {
     }
 }
 
+/// Opens the threads listed by a `ThreadIdIter`.
 #[derive(Debug)]
-pub struct ThreadIter<'a> {
-    pub(crate) process: &'a Process,
-    pub(crate) snapshot: Handle,
+pub struct ThreadIter {
+    pub(crate) ids: ThreadIdIter,
+    pub(crate) access: ThreadAccess,
 }
 
-impl<'a> Iterator for ThreadIter<'a> {
+impl Iterator for ThreadIter {
     type Item = WinResult<Thread>;
 
     fn next(&mut self) -> Option<WinResult<Thread>> {
-        unsafe {
-            loop {
-                let mut entry: THREADENTRY32 = mem::zeroed();
-                entry.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
-                let ret = Thread32Next(self.snapshot.as_raw_handle() as winnt::HANDLE, &mut entry);
-                if ret == 0 {
-                    return None;
-                } else {
-                    if entry.th32OwnerProcessID == self.process.id() {
-                        return Some(Thread::from_id(entry.th32ThreadID));
-                    }
-                }
-            }
-        }
+        let id = self.ids.next()?;
+        Some(Thread::from_id_with_access(id, self.access))
     }
 }
 
+/// Lists the ids of the threads of a process from a snapshot of all the threads of the system.
+///
+/// The id of the process is stored up front, since the snapshot lists every thread of the system.
 #[derive(Debug)]
-pub struct ThreadIdIter<'a> {
-    pub(crate) process: &'a Process,
+pub struct ThreadIdIter {
+    pub(crate) process_id: u32,
     pub(crate) snapshot: Handle,
+    pub(crate) started: bool,
 }
 
-impl<'a> Iterator for ThreadIdIter<'a> {
+impl Iterator for ThreadIdIter {
     type Item = u32;
 
     fn next(&mut self) -> Option<u32> {
         unsafe {
+            let snapshot = self.snapshot.as_raw_handle() as winnt::HANDLE;
             loop {
                 let mut entry: THREADENTRY32 = mem::zeroed();
                 entry.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
-                let ret = Thread32Next(self.snapshot.as_raw_handle() as winnt::HANDLE, &mut entry);
+                let ret = if self.started {
+                    Thread32Next(snapshot, &mut entry)
+                } else {
+                    self.started = true;
+                    Thread32First(snapshot, &mut entry)
+                };
                 if ret == 0 {
                     return None;
-                } else {
-                    if entry.th32OwnerProcessID == self.process.id() {
-                        return Some(entry.th32ThreadID);
-                    }
+                }
+                if entry.th32OwnerProcessID == self.process_id {
+                    return Some(entry.th32ThreadID);
                 }
             }
         }
     }
 }
 
+/// Holds data related to a running thread of the system.
+///
+/// Maps almost directly to a Windows [THREADENTRY32][THREADENTRY32].
+///
+/// [THREADENTRY32]: https://docs.microsoft.com/en-us/windows/desktop/api/tlhelp32/ns-tlhelp32-threadentry32
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ThreadEntry {
+    /// The identifier of the thread.
+    pub id: u32,
+    /// The identifier of the process that created the thread.
+    pub process_id: u32,
+    /// The base priority level assigned to the thread, from 0 to 31.
+    pub base_priority: i32,
+}
+
+impl ThreadEntry {
+    /// Opens the thread. Requests all access.
+    pub fn open(&self) -> WinResult<Thread> {
+        Thread::from_id(self.id)
+    }
+
+    /// Opens the thread with the specified access rights.
+    pub fn open_with_access(&self, access: ThreadAccess) -> WinResult<Thread> {
+        Thread::from_id_with_access(self.id, access)
+    }
+}
+
+impl From<THREADENTRY32> for ThreadEntry {
+    fn from(te: THREADENTRY32) -> ThreadEntry {
+        ThreadEntry {
+            id: te.th32ThreadID,
+            process_id: te.th32OwnerProcessID,
+            base_priority: te.tpBasePri,
+        }
+    }
+}
+
+/// Returns the number of threads running on the system, including the ones that can't be
+/// opened, without opening any.
+pub fn thread_count() -> WinResult<usize> {
+    Ok(ntdll::SystemProcesses::snapshot()?
+        .iter()
+        .map(|(_, threads)| threads.len())
+        .sum())
+}
+
+/// Lists the threads of all processes of the system, along with their owning process, from a
+/// single snapshot.
+pub fn all_threads_with_owner() -> WinResult<Vec<ThreadEntry>> {
+    unsafe {
+        let snap = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snap == INVALID_HANDLE_VALUE {
+            return Err(Error::last_os_error());
+        }
+        let snap = Handle::new(snap);
+
+        let mut threads = Vec::new();
+        let mut entry: THREADENTRY32 = mem::zeroed();
+        entry.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
+        let mut ret = Thread32First(*snap, &mut entry);
+        while ret != 0 {
+            threads.push(entry.into());
+            ret = Thread32Next(*snap, &mut entry);
+        }
+        Ok(threads)
+    }
+}
+
+/// Lists the threads of all processes of the system from a single snapshot, grouped by the id of
+/// their owning process.
+pub fn threads_by_process() -> WinResult<HashMap<u32, Vec<ThreadEntry>>> {
+    let mut map: HashMap<u32, Vec<ThreadEntry>> = HashMap::new();
+    for thread in all_threads_with_owner()? {
+        map.entry(thread.process_id).or_default().push(thread);
+    }
+    Ok(map)
+}
+
+/// The times of a thread, as returned by `Thread::times`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ThreadTimes {
+    /// When the thread was created.
+    pub creation: SystemTime,
+    /// When the thread exited, or `None` if it's still running.
+    pub exit: Option<SystemTime>,
+    /// The CPU time the thread has spent in kernel mode.
+    pub kernel: Duration,
+    /// The CPU time the thread has spent in user mode.
+    pub user: Duration,
+}
+
+/// The scheduling state of a thread.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ThreadState {
+    /// The thread is running or ready to run.
+    Running,
+    /// The thread is frozen by a positive suspend count.
+    Suspended,
+    /// The thread is blocked on a wait, for example on an object, a sleep, or paged-out memory.
+    Waiting,
+    /// The thread has exited.
+    Terminated,
+}
+
 /// A thread scheduling priority level.
 ///
 /// See [Scheduling Priorities](https://docs.microsoft.com/en-us/windows/desktop/procthread/scheduling-priorities)
@@ -411,16 +1100,18 @@ pub enum PriorityLevel {
 }
 
 impl PriorityLevel {
-    fn from_code(code: DWORD) -> PriorityLevel {
+    /// Returns `None` for the levels between the standard ones, which threads of processes in the
+    /// realtime priority class can have.
+    fn from_code(code: DWORD) -> Option<PriorityLevel> {
         match code {
-            THREAD_PRIORITY_IDLE => PriorityLevel::Idle,
-            THREAD_PRIORITY_LOWEST => PriorityLevel::Lowest,
-            THREAD_PRIORITY_BELOW_NORMAL => PriorityLevel::BelowNormal,
-            THREAD_PRIORITY_NORMAL => PriorityLevel::Normal,
-            THREAD_PRIORITY_ABOVE_NORMAL => PriorityLevel::AboveNormal,
-            THREAD_PRIORITY_HIGHEST => PriorityLevel::Highest,
-            THREAD_PRIORITY_TIME_CRITICAL => PriorityLevel::TimeCritical,
-            _ => panic!("Unexpected priority code: {}", code),
+            THREAD_PRIORITY_IDLE => Some(PriorityLevel::Idle),
+            THREAD_PRIORITY_LOWEST => Some(PriorityLevel::Lowest),
+            THREAD_PRIORITY_BELOW_NORMAL => Some(PriorityLevel::BelowNormal),
+            THREAD_PRIORITY_NORMAL => Some(PriorityLevel::Normal),
+            THREAD_PRIORITY_ABOVE_NORMAL => Some(PriorityLevel::AboveNormal),
+            THREAD_PRIORITY_HIGHEST => Some(PriorityLevel::Highest),
+            THREAD_PRIORITY_TIME_CRITICAL => Some(PriorityLevel::TimeCritical),
+            _ => None,
         }
     }
 
@@ -443,6 +1134,139 @@ impl Default for PriorityLevel {
     }
 }
 
+bitflags! {
+    /// Windows thread-related access permission flags.
+    pub struct ThreadAccess: u32 {
+        /// Required to delete the object.
+        const DELETE = winnt::DELETE;
+        /// Required to read information in the security descriptor for the object, not including
+        /// the information in the SACL.
+        const READ_CONTROL = winnt::READ_CONTROL;
+        /// Required to modify the DACL in the security descriptor for the object.
+        const WRITE_DAC = winnt::WRITE_DAC;
+        /// Required to change the owner in the security descriptor for the object.
+        const WRITE_OWNER = winnt::WRITE_OWNER;
+        /// The right to use the object for synchronization.
+        /// This enables a thread to wait until the object is in the signaled state.
+        const SYNCHRONIZE = winnt::SYNCHRONIZE;
+        /// Union of `DELETE | READ_CONTROL | WRITE_DAC | WRITE_OWNER`.
+        const STANDARD_RIGHTS_REQUIRED = winnt::STANDARD_RIGHTS_REQUIRED;
+        /// Required to terminate a thread.
+        const THREAD_TERMINATE = winnt::THREAD_TERMINATE;
+        /// Required to suspend or resume a thread.
+        const THREAD_SUSPEND_RESUME = winnt::THREAD_SUSPEND_RESUME;
+        /// Required to read the context of a thread.
+        const THREAD_GET_CONTEXT = winnt::THREAD_GET_CONTEXT;
+        /// Required to write the context of a thread.
+        const THREAD_SET_CONTEXT = winnt::THREAD_SET_CONTEXT;
+        /// Required to set certain information about a thread, such as its priority.
+        const THREAD_SET_INFORMATION = winnt::THREAD_SET_INFORMATION;
+        /// Required to retrieve certain information about a thread, such as its token, exit code
+        /// and times.
+        const THREAD_QUERY_INFORMATION = winnt::THREAD_QUERY_INFORMATION;
+        /// Required to set the impersonation token of a thread.
+        const THREAD_SET_THREAD_TOKEN = winnt::THREAD_SET_THREAD_TOKEN;
+        /// Required to use a thread's security information directly without using it through a
+        /// communication mechanism that provides impersonation services.
+        const THREAD_IMPERSONATE = winnt::THREAD_IMPERSONATE;
+        /// Required for a server thread that impersonates a client.
+        const THREAD_DIRECT_IMPERSONATION = winnt::THREAD_DIRECT_IMPERSONATION;
+        /// Required to set certain information about a thread, such as its description.
+        ///
+        /// A handle that has the `THREAD_SET_INFORMATION` access right is automatically granted
+        /// `THREAD_SET_LIMITED_INFORMATION`.
+        const THREAD_SET_LIMITED_INFORMATION = winnt::THREAD_SET_LIMITED_INFORMATION;
+        /// Required to retrieve certain information about a thread (exit code, priority, cycle
+        /// time, description).
+        ///
+        /// A handle that has the `THREAD_QUERY_INFORMATION` access right is automatically granted
+        /// `THREAD_QUERY_LIMITED_INFORMATION`.
+        const THREAD_QUERY_LIMITED_INFORMATION = winnt::THREAD_QUERY_LIMITED_INFORMATION;
+        /// Required to resume a thread, without suspending it.
+        const THREAD_RESUME = winnt::THREAD_RESUME;
+        /// All possible access rights for a thread object.
+        const THREAD_ALL_ACCESS = Self::STANDARD_RIGHTS_REQUIRED.bits | Self::SYNCHRONIZE.bits | 0xffff;
+    }
+}
+
+impl Default for ThreadAccess {
+    /// Returns `ThreadAccess::THREAD_ALL_ACCESS`.
+    fn default() -> ThreadAccess {
+        ThreadAccess::THREAD_ALL_ACCESS
+    }
+}
+
+bitflags! {
+    /// The parts of a thread's `CONTEXT` to retrieve with `Thread::context`.
+    pub struct ContextFlags: u32 {
+        /// The instruction and stack pointers, flags, and the code and stack segment selectors.
+        const CONTROL = winnt::CONTEXT_CONTROL;
+        /// The general purpose integer registers.
+        const INTEGER = winnt::CONTEXT_INTEGER;
+        /// The data segment selectors.
+        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+        const SEGMENTS = winnt::CONTEXT_SEGMENTS;
+        /// The floating point and vector registers.
+        const FLOATING_POINT = winnt::CONTEXT_FLOATING_POINT;
+        /// The hardware debug registers.
+        const DEBUG_REGISTERS = winnt::CONTEXT_DEBUG_REGISTERS;
+        /// The extended (SSE) registers.
+        #[cfg(target_arch = "x86")]
+        const EXTENDED_REGISTERS = winnt::CONTEXT_EXTENDED_REGISTERS;
+        /// The registers Windows considers the full context of the current architecture.
+        const FULL = winnt::CONTEXT_FULL;
+        /// All registers except the extended state.
+        const ALL = winnt::CONTEXT_ALL;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Restores the priority level of a thread when dropped, so a failing test doesn't leave the
+    /// test runner's thread at an unusual level.
+    struct PriorityGuard {
+        thread: Thread,
+        original: PriorityLevel,
+    }
+
+    impl Drop for PriorityGuard {
+        fn drop(&mut self) {
+            let _ = self.thread.set_priority(self.original);
+        }
+    }
+
+    #[test]
+    fn round_trips_thread_priority_levels() {
+        let thread = Thread::current();
+        let original = thread.priority().unwrap();
+        let mut guard = PriorityGuard { thread, original };
+        let levels = [
+            PriorityLevel::Idle,
+            PriorityLevel::Lowest,
+            PriorityLevel::BelowNormal,
+            PriorityLevel::Normal,
+            PriorityLevel::AboveNormal,
+            PriorityLevel::Highest,
+            PriorityLevel::TimeCritical,
+        ];
+        for &level in &levels {
+            guard.thread.set_priority(level).unwrap();
+            assert_eq!(guard.thread.priority().unwrap(), level);
+        }
+    }
+
+    #[test]
+    fn round_trips_thread_descriptions() {
+        let mut thread = Thread::current();
+        thread.set_description("winproc test").unwrap();
+        assert_eq!(thread.description().unwrap(), "winproc test");
+        thread.set_description("").unwrap();
+        assert_eq!(thread.description().unwrap(), "");
+    }
+}
+
 //mod tests {
 //    #[allow(unused_imports)]
 //    use super::*;
@@ -0,0 +1,354 @@
+use crate::{Error, Handle, WinResult};
+use std::{
+    io,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    os::windows::io::{AsRawHandle, FromRawHandle, IntoRawHandle, RawHandle},
+    ptr::null_mut,
+    slice,
+};
+use winapi::{
+    shared::{sddl::ConvertSidToStringSidW, winerror::ERROR_INSUFFICIENT_BUFFER},
+    um::{
+        errhandlingapi::GetLastError,
+        securitybaseapi::{
+            DuplicateTokenEx,
+            GetSidSubAuthority,
+            GetSidSubAuthorityCount,
+            GetTokenInformation,
+            ImpersonateLoggedOnUser,
+            RevertToSelf,
+        },
+        winbase::{LocalFree, LookupAccountSidW},
+        winnt::{
+            self,
+            SecurityImpersonation,
+            TokenAppContainerSid,
+            TokenElevation,
+            TokenImpersonation,
+            TokenIntegrityLevel,
+            TokenIsAppContainer,
+            TokenUser,
+            PSID,
+            SECURITY_MANDATORY_HIGH_RID,
+            SECURITY_MANDATORY_LOW_RID,
+            SECURITY_MANDATORY_MEDIUM_RID,
+            SECURITY_MANDATORY_SYSTEM_RID,
+            TOKEN_APPCONTAINER_INFORMATION,
+            TOKEN_ELEVATION,
+            TOKEN_INFORMATION_CLASS,
+            TOKEN_MANDATORY_LABEL,
+            TOKEN_USER,
+            WCHAR,
+        },
+    },
+};
+
+/// A handle to an access token, which describes the security context of a process or thread.
+#[derive(Debug)]
+pub struct Token {
+    handle: Handle,
+}
+
+impl Token {
+    /// Creates a token from a handle.
+    pub fn from_handle(handle: Handle) -> Token {
+        Token { handle }
+    }
+
+    /// Returns a reference to the inner handle.
+    pub fn handle(&self) -> &Handle {
+        &self.handle
+    }
+
+    /// Returns true if the token belongs to an AppContainer.
+    ///
+    /// The token must have been opened with the `TOKEN_QUERY` access right.
+    pub fn is_app_container(&self) -> WinResult<bool> {
+        let ret: u32 = self.information_value(TokenIsAppContainer)?;
+        Ok(ret != 0)
+    }
+
+    /// Returns the string form of the AppContainer SID of the token, or `None` if the token
+    /// doesn't belong to an AppContainer.
+    ///
+    /// The token must have been opened with the `TOKEN_QUERY` access right.
+    pub fn app_container_sid(&self) -> WinResult<Option<String>> {
+        let buffer = self.information(TokenAppContainerSid)?;
+        unsafe {
+            let info = &*(buffer.as_ptr() as *const TOKEN_APPCONTAINER_INFORMATION);
+            if info.TokenAppContainer.is_null() {
+                Ok(None)
+            } else {
+                sid_to_string(info.TokenAppContainer).map(Some)
+            }
+        }
+    }
+
+    /// Returns the name of the token's user, in the `DOMAIN\user` form.
+    ///
+    /// The token must have been opened with the `TOKEN_QUERY` access right.
+    pub fn user(&self) -> WinResult<String> {
+        let buffer = self.information(TokenUser)?;
+        unsafe {
+            let info = &*(buffer.as_ptr() as *const TOKEN_USER);
+            lookup_account_name(info.User.Sid)
+        }
+    }
+
+    /// Returns true if the token is elevated, meaning UAC granted it its full administrative
+    /// rights.
+    ///
+    /// The token must have been opened with the `TOKEN_QUERY` access right.
+    pub fn is_elevated(&self) -> WinResult<bool> {
+        let ret: TOKEN_ELEVATION = self.information_value(TokenElevation)?;
+        Ok(ret.TokenIsElevated != 0)
+    }
+
+    /// Returns the mandatory integrity level of the token.
+    ///
+    /// The token must have been opened with the `TOKEN_QUERY` access right.
+    pub fn integrity_level(&self) -> WinResult<IntegrityLevel> {
+        let buffer = self.information(TokenIntegrityLevel)?;
+        unsafe {
+            let info = &*(buffer.as_ptr() as *const TOKEN_MANDATORY_LABEL);
+            let sid = info.Label.Sid;
+            // The RID is the last subauthority of the label SID.
+            let count = *GetSidSubAuthorityCount(sid);
+            let rid = *GetSidSubAuthority(sid, u32::from(count) - 1);
+            Ok(IntegrityLevel::from_rid(rid))
+        }
+    }
+
+    /// Duplicates the token into an impersonation token with the `SecurityImpersonation` level,
+    /// which lets the current process act as the token's user on the local system.
+    ///
+    /// The token must have been opened with the `TOKEN_DUPLICATE` access right. The new token has
+    /// all the access rights the caller is allowed.
+    pub fn duplicate_for_impersonation(&self) -> WinResult<Token> {
+        unsafe {
+            let mut token = null_mut();
+            let ret = DuplicateTokenEx(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                winnt::MAXIMUM_ALLOWED,
+                null_mut(),
+                SecurityImpersonation,
+                TokenImpersonation,
+                &mut token,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(Token::from_handle(Handle::new(token)))
+            }
+        }
+    }
+
+    /// Makes the current thread impersonate the token's user until the returned guard is dropped.
+    ///
+    /// A primary token must have been opened with the `TOKEN_QUERY` and `TOKEN_DUPLICATE` access
+    /// rights, and an impersonation token with the `TOKEN_QUERY` and `TOKEN_IMPERSONATE` access
+    /// rights.
+    pub fn impersonate(&self) -> WinResult<ImpersonationGuard> {
+        unsafe {
+            let ret = ImpersonateLoggedOnUser(self.handle.as_raw_handle() as winnt::HANDLE);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(ImpersonationGuard {
+                    reverted: false,
+                    _not_send: PhantomData,
+                })
+            }
+        }
+    }
+
+    /// Queries a fixed-size token information class.
+    fn information_value<T: Copy>(&self, class: TOKEN_INFORMATION_CLASS) -> WinResult<T> {
+        unsafe {
+            let mut value: T = mem::zeroed();
+            let mut len = 0;
+            let ret = GetTokenInformation(
+                self.handle.as_raw_handle() as winnt::HANDLE,
+                class,
+                &mut value as *mut T as _,
+                mem::size_of::<T>() as _,
+                &mut len,
+            );
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(value)
+            }
+        }
+    }
+
+    /// Queries a variable-size token information class. The returned buffer is suitably aligned
+    /// to be cast to the class's structure.
+    fn information(&self, class: TOKEN_INFORMATION_CLASS) -> WinResult<Vec<u64>> {
+        unsafe {
+            let handle = self.handle.as_raw_handle() as winnt::HANDLE;
+            let mut len = 0;
+            let ret = GetTokenInformation(handle, class, null_mut(), 0, &mut len);
+            if ret == 0 && GetLastError() != ERROR_INSUFFICIENT_BUFFER {
+                return Err(Error::last_os_error());
+            }
+
+            let mut buffer = vec![0u64; len as usize / 8 + 1];
+            let ret = GetTokenInformation(handle, class, buffer.as_mut_ptr() as _, len, &mut len);
+            if ret == 0 {
+                Err(Error::last_os_error())
+            } else {
+                Ok(buffer)
+            }
+        }
+    }
+}
+
+impl AsRawHandle for Token {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.handle.as_raw_handle()
+    }
+}
+
+impl Deref for Token {
+    type Target = winnt::HANDLE;
+
+    fn deref(&self) -> &winnt::HANDLE {
+        &self.handle
+    }
+}
+
+impl FromRawHandle for Token {
+    unsafe fn from_raw_handle(handle: RawHandle) -> Token {
+        Token {
+            handle: Handle::new(handle as winnt::HANDLE),
+        }
+    }
+}
+
+impl IntoRawHandle for Token {
+    fn into_raw_handle(self) -> RawHandle {
+        self.handle.into_raw_handle()
+    }
+}
+
+/// A mandatory integrity level, which restricts what a process can access regardless of its
+/// user.
+///
+/// See [Mandatory Integrity Control](https://docs.microsoft.com/en-us/windows/desktop/secauthz/mandatory-integrity-control)
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum IntegrityLevel {
+    Untrusted,
+    Low,
+    Medium,
+    High,
+    System,
+}
+
+impl IntegrityLevel {
+    /// Maps a RID to its level. RIDs between the standard ones, like medium plus, are rounded
+    /// down.
+    fn from_rid(rid: u32) -> IntegrityLevel {
+        if rid < SECURITY_MANDATORY_LOW_RID {
+            IntegrityLevel::Untrusted
+        } else if rid < SECURITY_MANDATORY_MEDIUM_RID {
+            IntegrityLevel::Low
+        } else if rid < SECURITY_MANDATORY_HIGH_RID {
+            IntegrityLevel::Medium
+        } else if rid < SECURITY_MANDATORY_SYSTEM_RID {
+            IntegrityLevel::High
+        } else {
+            IntegrityLevel::System
+        }
+    }
+}
+
+/// The impersonation of a token's user by the current thread, started by `Token::impersonate`.
+///
+/// Reverts the thread to its own security context when dropped. Impersonation only applies to
+/// the thread that started it, so the guard can't be sent to another thread.
+#[derive(Debug)]
+pub struct ImpersonationGuard {
+    reverted: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ImpersonationGuard {
+    /// Reverts the current thread to its own security context, reporting failure instead of
+    /// panicking.
+    pub fn revert(mut self) -> WinResult {
+        self.reverted = true;
+        if unsafe { RevertToSelf() } == 0 {
+            Err(Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for ImpersonationGuard {
+    fn drop(&mut self) {
+        if !self.reverted {
+            // Carrying on as another user would be a security hole, so failing is fatal.
+            let ret = unsafe { RevertToSelf() };
+            assert_ne!(ret, 0, "{:?}", io::Error::last_os_error());
+        }
+    }
+}
+
+/// Converts a SID to its string form, such as `S-1-5-18`.
+unsafe fn sid_to_string(sid: PSID) -> WinResult<String> {
+    let mut string = null_mut();
+    if ConvertSidToStringSidW(sid, &mut string) == 0 {
+        return Err(Error::last_os_error());
+    }
+    let len = (0..).take_while(|&i| *string.offset(i) != 0).count();
+    let ret = String::from_utf16_lossy(slice::from_raw_parts(string, len));
+    LocalFree(string as _);
+    Ok(ret)
+}
+
+/// Resolves a SID to the name of its account, in the `DOMAIN\user` form. Accounts without a
+/// domain, like `Everyone`, have no prefix.
+unsafe fn lookup_account_name(sid: PSID) -> WinResult<String> {
+    let mut name_len = 0;
+    let mut domain_len = 0;
+    let mut sid_use = 0;
+    let ret = LookupAccountSidW(
+        null_mut(),
+        sid,
+        null_mut(),
+        &mut name_len,
+        null_mut(),
+        &mut domain_len,
+        &mut sid_use,
+    );
+    if ret == 0 && GetLastError() != ERROR_INSUFFICIENT_BUFFER {
+        return Err(Error::last_os_error());
+    }
+
+    // On success, the lengths no longer include the nul terminators.
+    let mut name: Vec<WCHAR> = vec![0; name_len as usize];
+    let mut domain: Vec<WCHAR> = vec![0; domain_len as usize];
+    let ret = LookupAccountSidW(
+        null_mut(),
+        sid,
+        name.as_mut_ptr(),
+        &mut name_len,
+        domain.as_mut_ptr(),
+        &mut domain_len,
+        &mut sid_use,
+    );
+    if ret == 0 {
+        return Err(Error::last_os_error());
+    }
+    let name = String::from_utf16_lossy(&name[..name_len as usize]);
+    if domain_len == 0 {
+        Ok(name)
+    } else {
+        let domain = String::from_utf16_lossy(&domain[..domain_len as usize]);
+        Ok(format!("{}\\{}", domain, name))
+    }
+}
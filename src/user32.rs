@@ -0,0 +1,19 @@
+//! Bindings to the `user32` APIs not covered by `winapi`.
+
+#![allow(non_snake_case)]
+
+use winapi::{shared::minwindef::DWORD, um::winnt::HANDLE};
+
+/// `GetGuiResources` flag for the number of GDI objects in use.
+pub const GR_GDIOBJECTS: DWORD = 0;
+/// `GetGuiResources` flag for the number of USER objects in use.
+pub const GR_USEROBJECTS: DWORD = 1;
+/// `GetGuiResources` flag for the peak number of GDI objects used.
+pub const GR_GDIOBJECTS_PEAK: DWORD = 2;
+/// `GetGuiResources` flag for the peak number of USER objects used.
+pub const GR_USEROBJECTS_PEAK: DWORD = 4;
+
+#[link(name = "user32")]
+extern "system" {
+    pub fn GetGuiResources(hProcess: HANDLE, uiFlags: DWORD) -> DWORD;
+}
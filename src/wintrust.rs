@@ -0,0 +1,270 @@
+//! Authenticode verification of files, with bindings to the catalog APIs not covered by `winapi`.
+
+#![allow(non_snake_case, non_camel_case_types, clippy::upper_case_acronyms)]
+
+use crate::{dynamic, Error, WinResult};
+use std::{fs::File, mem, os::windows::io::AsRawHandle, path::Path, ptr::null_mut};
+use widestring::WideCString;
+use winapi::{
+    ctypes::c_void,
+    shared::{
+        guiddef::GUID,
+        minwindef::{BOOL, BYTE, DWORD, MAX_PATH},
+        ntdef::{HANDLE, LONG, LPCWSTR, WCHAR},
+        winerror::{TRUST_E_NOSIGNATURE, TRUST_E_PROVIDER_UNKNOWN, TRUST_E_SUBJECT_FORM_UNKNOWN},
+    },
+    um::{
+        softpub::WINTRUST_ACTION_GENERIC_VERIFY_V2,
+        wintrust::{
+            WinVerifyTrust,
+            WINTRUST_DATA,
+            WINTRUST_FILE_INFO,
+            WTD_CHOICE_CATALOG,
+            WTD_CHOICE_FILE,
+            WTD_REVOKE_NONE,
+            WTD_STATEACTION_CLOSE,
+            WTD_STATEACTION_VERIFY,
+            WTD_UI_NONE,
+        },
+    },
+};
+
+type HCATADMIN = HANDLE;
+type HCATINFO = HANDLE;
+
+#[repr(C)]
+struct CATALOG_INFO {
+    cbStruct: DWORD,
+    wszCatalogFile: [WCHAR; MAX_PATH],
+}
+
+#[repr(C)]
+struct WINTRUST_CATALOG_INFO {
+    cbStruct: DWORD,
+    dwCatalogVersion: DWORD,
+    pcwszCatalogFilePath: LPCWSTR,
+    pcwszMemberTag: LPCWSTR,
+    pcwszMemberFilePath: LPCWSTR,
+    hMemberFile: HANDLE,
+    pbCalculatedFileHash: *mut BYTE,
+    cbCalculatedFileHash: DWORD,
+    pcCatalogContext: *const c_void,
+    hCatAdmin: HCATADMIN,
+}
+
+#[link(name = "wintrust")]
+extern "system" {
+    fn CryptCATAdminAcquireContext(
+        phCatAdmin: *mut HCATADMIN,
+        pgSubsystem: *const GUID,
+        dwFlags: DWORD,
+    ) -> BOOL;
+
+    fn CryptCATAdminCalcHashFromFileHandle(
+        hFile: HANDLE,
+        pcbHash: *mut DWORD,
+        pbHash: *mut BYTE,
+        dwFlags: DWORD,
+    ) -> BOOL;
+
+    fn CryptCATAdminEnumCatalogFromHash(
+        hCatAdmin: HCATADMIN,
+        pbHash: *mut BYTE,
+        cbHash: DWORD,
+        dwFlags: DWORD,
+        phPrevCatInfo: *mut HCATINFO,
+    ) -> HCATINFO;
+
+    fn CryptCATCatalogInfoFromContext(
+        hCatInfo: HCATINFO,
+        psCatInfo: *mut CATALOG_INFO,
+        dwFlags: DWORD,
+    ) -> BOOL;
+
+    fn CryptCATAdminReleaseCatalogContext(
+        hCatAdmin: HCATADMIN,
+        hCatInfo: HCATINFO,
+        dwFlags: DWORD,
+    ) -> BOOL;
+
+    fn CryptCATAdminReleaseContext(hCatAdmin: HCATADMIN, dwFlags: DWORD) -> BOOL;
+}
+
+/// `CryptCATAdminAcquireContext2`, available since Windows 8.
+type AcquireContext2Fn =
+    unsafe extern "system" fn(*mut HCATADMIN, *const GUID, LPCWSTR, *const c_void, DWORD) -> BOOL;
+
+/// `CryptCATAdminCalcHashFromFileHandle2`, available since Windows 8.
+type CalcHashFromFileHandle2Fn =
+    unsafe extern "system" fn(HCATADMIN, HANDLE, *mut DWORD, *mut BYTE, DWORD) -> BOOL;
+
+/// Returns true if the file has a valid Authenticode signature, either embedded in it or in a
+/// catalog registered on the system, as most Windows system files have.
+///
+/// Revocation isn't checked, to avoid network access.
+pub fn verify_file(path: &Path) -> WinResult<bool> {
+    let wide_path = WideCString::from_os_str(path).map_err(|e| Error::NulErrorW {
+        pos: e.nul_position(),
+        data: e.into_vec(),
+    })?;
+    let mut file_info: WINTRUST_FILE_INFO = unsafe { mem::zeroed() };
+    file_info.cbStruct = mem::size_of::<WINTRUST_FILE_INFO>() as _;
+    file_info.pcwszFilePath = wide_path.as_ptr();
+
+    match verify_trust(WTD_CHOICE_FILE, &mut file_info as *mut _ as _) {
+        0 => Ok(true),
+        // Only look for a catalog if the file isn't signed at all, not if its signature is bad.
+        TRUST_E_NOSIGNATURE | TRUST_E_SUBJECT_FORM_UNKNOWN | TRUST_E_PROVIDER_UNKNOWN => {
+            verify_catalog(path, &wide_path)
+        }
+        _ => Ok(false),
+    }
+}
+
+/// Looks for the file's hash in the system catalogs, and verifies the catalog signing it.
+fn verify_catalog(path: &Path, wide_path: &WideCString) -> WinResult<bool> {
+    let file = File::open(path)?;
+    // Recent catalogs index files by their SHA-256 hash, older ones by their SHA-1 hash.
+    let acquirers: [fn() -> Option<CatalogAdmin>; 2] = [CatalogAdmin::sha256, CatalogAdmin::sha1];
+    for acquire in acquirers.iter() {
+        let admin = match acquire() {
+            Some(admin) => admin,
+            None => continue,
+        };
+        let mut hash = match admin.hash(&file) {
+            Some(hash) => hash,
+            None => continue,
+        };
+        let tag: String = hash.iter().map(|byte| format!("{:02X}", byte)).collect();
+        let tag = WideCString::from_str(tag).unwrap();
+
+        unsafe {
+            let mut previous = null_mut();
+            let catalog = CryptCATAdminEnumCatalogFromHash(
+                admin.handle,
+                hash.as_mut_ptr(),
+                hash.len() as _,
+                0,
+                &mut previous,
+            );
+            if catalog.is_null() {
+                continue;
+            }
+            let mut catalog_info: CATALOG_INFO = mem::zeroed();
+            catalog_info.cbStruct = mem::size_of::<CATALOG_INFO>() as _;
+            let found = CryptCATCatalogInfoFromContext(catalog, &mut catalog_info, 0) != 0;
+            let status = if found {
+                let mut info: WINTRUST_CATALOG_INFO = mem::zeroed();
+                info.cbStruct = mem::size_of::<WINTRUST_CATALOG_INFO>() as _;
+                info.pcwszCatalogFilePath = catalog_info.wszCatalogFile.as_ptr();
+                info.pcwszMemberTag = tag.as_ptr();
+                info.pcwszMemberFilePath = wide_path.as_ptr();
+                info.hMemberFile = file.as_raw_handle() as HANDLE;
+                info.pbCalculatedFileHash = hash.as_mut_ptr();
+                info.cbCalculatedFileHash = hash.len() as _;
+                info.hCatAdmin = admin.handle;
+                verify_trust(WTD_CHOICE_CATALOG, &mut info as *mut _ as _)
+            } else {
+                TRUST_E_NOSIGNATURE
+            };
+            CryptCATAdminReleaseCatalogContext(admin.handle, catalog, 0);
+            if found {
+                return Ok(status == 0);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// Calls `WinVerifyTrust` with the generic Authenticode policy and no UI, and releases its
+/// state. Returns the resulting status.
+fn verify_trust(choice: DWORD, subject: *mut c_void) -> LONG {
+    unsafe {
+        let mut data: WINTRUST_DATA = mem::zeroed();
+        data.cbStruct = mem::size_of::<WINTRUST_DATA>() as _;
+        data.dwUIChoice = WTD_UI_NONE;
+        data.fdwRevocationChecks = WTD_REVOKE_NONE;
+        data.dwUnionChoice = choice;
+        // The union is only declared with its file member, but all members are pointers.
+        *data.u.pFile_mut() = subject as *mut WINTRUST_FILE_INFO;
+        data.dwStateAction = WTD_STATEACTION_VERIFY;
+
+        let mut action = WINTRUST_ACTION_GENERIC_VERIFY_V2;
+        let status = WinVerifyTrust(null_mut(), &mut action, &mut data as *mut _ as _);
+        data.dwStateAction = WTD_STATEACTION_CLOSE;
+        WinVerifyTrust(null_mut(), &mut action, &mut data as *mut _ as _);
+        status
+    }
+}
+
+/// A catalog administrator context, which determines the hash algorithm used to look up files.
+struct CatalogAdmin {
+    handle: HCATADMIN,
+    calc_hash2: Option<CalcHashFromFileHandle2Fn>,
+}
+
+impl CatalogAdmin {
+    /// Acquires a context using SHA-256, or returns `None` before Windows 8.
+    fn sha256() -> Option<CatalogAdmin> {
+        let acquire = dynamic::proc_address("wintrust", "CryptCATAdminAcquireContext2")?;
+        let calc_hash = dynamic::proc_address("wintrust", "CryptCATAdminCalcHashFromFileHandle2")?;
+        unsafe {
+            let acquire = mem::transmute::<*mut c_void, AcquireContext2Fn>(acquire);
+            let calc_hash = mem::transmute::<*mut c_void, CalcHashFromFileHandle2Fn>(calc_hash);
+            let algorithm = WideCString::from_str("SHA256").unwrap();
+            let mut handle = null_mut();
+            if acquire(&mut handle, null_mut(), algorithm.as_ptr(), null_mut(), 0) == 0 {
+                None
+            } else {
+                Some(CatalogAdmin {
+                    handle,
+                    calc_hash2: Some(calc_hash),
+                })
+            }
+        }
+    }
+
+    /// Acquires a context using SHA-1.
+    fn sha1() -> Option<CatalogAdmin> {
+        unsafe {
+            let mut handle = null_mut();
+            if CryptCATAdminAcquireContext(&mut handle, null_mut(), 0) == 0 {
+                None
+            } else {
+                Some(CatalogAdmin {
+                    handle,
+                    calc_hash2: None,
+                })
+            }
+        }
+    }
+
+    /// Computes the hash identifying the file in catalogs.
+    fn hash(&self, file: &File) -> Option<Vec<u8>> {
+        let handle = file.as_raw_handle() as HANDLE;
+        let calc = |len: &mut DWORD, hash: *mut BYTE| unsafe {
+            match self.calc_hash2 {
+                Some(calc_hash2) => calc_hash2(self.handle, handle, len, hash, 0),
+                None => CryptCATAdminCalcHashFromFileHandle(handle, len, hash, 0),
+            }
+        };
+        let mut len = 0;
+        calc(&mut len, null_mut());
+        if len == 0 {
+            return None;
+        }
+        let mut hash = vec![0; len as usize];
+        if calc(&mut len, hash.as_mut_ptr()) == 0 {
+            None
+        } else {
+            hash.truncate(len as usize);
+            Some(hash)
+        }
+    }
+}
+
+impl Drop for CatalogAdmin {
+    fn drop(&mut self) {
+        unsafe { CryptCATAdminReleaseContext(self.handle, 0) };
+    }
+}